@@ -1,5 +1,7 @@
 use RustQuant_autodiff::{Accumulate, Graph};
+use aad::gradients::GradientBuffer;
 use aad::tape::Tape;
+use aad::variable::Variable;
 use aad_derive::autodiff;
 use criterion::{Criterion, criterion_group, criterion_main};
 use std::hint::black_box;
@@ -42,6 +44,80 @@ fn large_computation_graph_benchmark(c: &mut Criterion) {
     });
 }
 
+fn large_computation_graph_record_benchmark(c: &mut Criterion) {
+    c.bench_function("large_computation_graph_record", |b| {
+        let tape = Tape::default();
+        let [x0, x1, x2, x3, x4] = tape.create_variables(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let mark = tape.mark();
+        b.iter(|| {
+            let result = build_calculation_graph(x0, x1, x2, x3, x4);
+            black_box(result);
+            tape.rewind_to(mark);
+        });
+    });
+}
+
+fn large_computation_graph_sweep_benchmark(c: &mut Criterion) {
+    c.bench_function("large_computation_graph_sweep", |b| {
+        let tape = Tape::default();
+        let [x0, x1, x2, x3, x4] = tape.create_variables(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let result = build_calculation_graph(x0, x1, x2, x3, x4);
+        b.iter(|| {
+            let grads = result.compute_gradients().unwrap();
+            black_box(grads);
+        });
+    });
+}
+
+fn large_computation_graph_sweep_into_benchmark(c: &mut Criterion) {
+    c.bench_function("large_computation_graph_sweep_into", |b| {
+        let tape = Tape::default();
+        let [x0, x1, x2, x3, x4] = tape.create_variables(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let result = build_calculation_graph(x0, x1, x2, x3, x4);
+        let mut buffer = GradientBuffer::new();
+        b.iter(|| {
+            let grads = result.compute_gradients_into(&mut buffer).unwrap();
+            black_box(grads.get_gradient(&x0).unwrap());
+        });
+    });
+}
+
+/// Records 100 trades on one tape and returns the market inputs and the price of the last one.
+fn record_portfolio(tape: &Tape<f64>) -> ([Variable<'_, f64>; 5], Variable<'_, f64>) {
+    let market = tape.create_variables(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+    let [x0, x1, x2, x3, x4] = market;
+    let mut price = x0;
+    for trade in 0..100 {
+        price = x0;
+        for i in 0..1_000 {
+            price +=
+                (((price + x1) * x2.sin()) + (x3 * x4.ln())) * (x2 + f64::from(trade + i).ln());
+        }
+    }
+    (market, price)
+}
+
+fn portfolio_trade_sweep_benchmark(c: &mut Criterion) {
+    c.bench_function("portfolio_trade_sweep", |b| {
+        let tape = Tape::default();
+        let (market, price) = record_portfolio(&tape);
+        b.iter(|| {
+            let grads = price.compute_gradients().unwrap();
+            black_box(grads.get_gradients(&market).unwrap());
+        });
+    });
+}
+
+fn portfolio_trade_pruned_sweep_benchmark(c: &mut Criterion) {
+    c.bench_function("portfolio_trade_pruned_sweep", |b| {
+        let tape = Tape::default();
+        let (market, price) = record_portfolio(&tape);
+        b.iter(|| {
+            black_box(tape.pruned_gradients(&price, &market).unwrap());
+        });
+    });
+}
+
 fn large_computation_graph_benchmark_rust_quant(c: &mut Criterion) {
     c.bench_function("large_computation_graph_rust_quant", |b| {
         let tape = Graph::default();
@@ -82,6 +158,11 @@ criterion_group!(
     benches,
     large_computation_graph_benchmark,
     large_computation_graph_benchmark_derive,
+    large_computation_graph_record_benchmark,
+    large_computation_graph_sweep_benchmark,
+    large_computation_graph_sweep_into_benchmark,
+    portfolio_trade_sweep_benchmark,
+    portfolio_trade_pruned_sweep_benchmark,
     large_computation_graph_benchmark_rust_quant,
     large_computation_graph_benchmark_f64
 );
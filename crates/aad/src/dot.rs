@@ -0,0 +1,288 @@
+//! Graphviz DOT export of the nodes recorded on a tape.
+//!
+//! Each node is drawn as a box labelled with its index and, when they are known, its operation
+//! kind, label, value and adjoint. Each parent is joined to its child by an edge labelled with
+//! the local partial derivative; the outputs of an external record are joined to its inputs by
+//! dashed edges.
+//!
+//! The tape keeps no node values, so a value is shown for the variables given to
+//! [`DotOptions`] and, on tapes recording operation kinds, for the variables created with
+//! [`Tape::create_variable`].
+
+use crate::gradients::Gradients;
+use crate::replay::OpKind;
+use crate::tape::Tape;
+use crate::variable::Variable;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// What to show in a DOT export besides the recorded nodes.
+///
+/// # Examples
+///
+/// ```
+/// use aad::Tape;
+/// use aad::dot::DotOptions;
+///
+/// let tape = Tape::new().with_op_kinds();
+/// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+/// let z = x * y;
+/// let grads = z.compute_gradients().unwrap();
+///
+/// let dot = tape.to_dot_with(
+///     &DotOptions::new()
+///         .with_label(&x, "spot")
+///         .with_output(&z)
+///         .with_gradients(&grads),
+/// );
+/// assert!(dot.contains(r##"n0 [label="#0 input\nspot\nvalue = 2\nadjoint = 3""##));
+/// assert!(dot.contains(r#"n0 -> n2 [label="3"]"#));
+/// ```
+#[derive(Debug)]
+pub struct DotOptions<'a, F> {
+    labels: HashMap<usize, (String, F)>,
+    output: Option<(usize, F)>,
+    adjoints: Option<&'a [F]>,
+}
+
+impl<F> Default for DotOptions<'_, F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> DotOptions<'_, F> {
+    #[inline]
+    #[must_use]
+    /// Creates options that add nothing to the recorded nodes.
+    pub fn new() -> Self {
+        Self {
+            labels: HashMap::new(),
+            output: None,
+            adjoints: None,
+        }
+    }
+}
+
+impl<'a, F: Copy> DotOptions<'a, F> {
+    #[inline]
+    #[must_use]
+    /// Labels the node of `var` with `label` and shows its value.
+    ///
+    /// Constants have no node and are ignored.
+    pub fn with_label(mut self, var: &Variable<'_, F>, label: impl Into<String>) -> Self {
+        if let Some((index, _)) = var.index {
+            self.labels.insert(index, (label.into(), var.value));
+        }
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Highlights the nodes `output` depends on and shows its value, greying out the rest.
+    ///
+    /// A constant has no node, so every node is then greyed out.
+    pub fn with_output(mut self, output: &Variable<'_, F>) -> Self {
+        self.output = Some((output.index.map_or(usize::MAX, |(i, _)| i), output.value));
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Shows the adjoints of a sweep, shading each node by the magnitude of its adjoint.
+    pub fn with_gradients<S: AsRef<[F]>>(mut self, gradients: &'a Gradients<F, S>) -> Self {
+        self.adjoints = Some(gradients.as_slice());
+        self
+    }
+}
+
+/// Escapes `text` for use inside a quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl<F: Copy + Display + Into<f64>> Tape<F> {
+    #[inline]
+    #[must_use]
+    /// Renders the recorded nodes as a Graphviz DOT graph.
+    ///
+    /// See [`Tape::to_dot_with`] to label nodes, highlight an output or show adjoints.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let x = tape.create_variable(2.0_f64);
+    /// let _ = x.sin();
+    /// assert_eq!(
+    ///     tape.to_dot(),
+    ///     "digraph tape {\n    node [shape=box];\n    n0 [label=\"#0\"];\n    n1 [label=\"#1\"];\n    n0 -> n1 [label=\"-0.4161468365471424\"];\n}\n"
+    /// );
+    /// ```
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DotOptions::new())
+    }
+
+    #[must_use]
+    /// Renders the recorded nodes as a Graphviz DOT graph, with the additions of `options`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Labels, output and adjoints to show
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The graph in DOT syntax, to be rendered with e.g. `dot -Tsvg`
+    pub fn to_dot_with(&self, options: &DotOptions<'_, F>) -> String {
+        let len = self.len();
+        let mut edges = Vec::new();
+        self.operations
+            .borrow()
+            .for_each(0..len, |node, indices, partials| {
+                for (&index, &partial) in indices.iter().zip(partials) {
+                    edges.push((index as usize, node, Some(partial)));
+                }
+            });
+        for external in self.externals.borrow().iter() {
+            for output in external.first..external.first + external.outputs {
+                for &input in external.inputs.iter().filter(|&&input| input != usize::MAX) {
+                    edges.push((input, output, None));
+                }
+            }
+        }
+
+        let reachable = options.output.map(|(output, _)| {
+            let mut reachable = if output < len {
+                self.cone(&[output])
+            } else {
+                Vec::new()
+            };
+            reachable.resize(len, false);
+            reachable
+        });
+        let highest = options.adjoints.map_or(0.0, |adjoints| {
+            adjoints
+                .iter()
+                .map(|&adjoint| adjoint.into().abs())
+                .fold(0.0, f64::max)
+        });
+
+        let ops = self.ops.borrow();
+        let mut lines = vec![
+            "digraph tape {".to_owned(),
+            "    node [shape=box];".to_owned(),
+        ];
+        for node in 0..len {
+            let kind = ops.as_ref().map(|log| log.kind(node));
+            let mut label = format!("#{node}");
+            if let Some(kind) = kind.filter(|&kind| kind != OpKind::Opaque) {
+                label = format!("{label} {kind}");
+            }
+            let value = match (options.labels.get(&node), options.output) {
+                (Some((text, value)), _) => {
+                    label = format!("{label}\\n{}", escape(text));
+                    Some(*value)
+                }
+                (None, Some((output, value))) if output == node => Some(value),
+                _ if kind == Some(OpKind::Input) => {
+                    ops.as_ref().map(|log| log.constants(node)[0].1)
+                }
+                _ => None,
+            };
+            if let Some(value) = value {
+                label = format!("{label}\\nvalue = {value}");
+            }
+            let adjoint = options.adjoints.and_then(|adjoints| adjoints.get(node));
+            if let Some(adjoint) = adjoint {
+                label = format!("{label}\\nadjoint = {adjoint}");
+            }
+
+            let mut attributes = vec![format!("label=\"{label}\"")];
+            let magnitude = adjoint.map_or(0.0, |&adjoint| adjoint.into().abs());
+            if magnitude > 0.0 {
+                let shade = 1.0 + (8.0 * magnitude / highest).round();
+                attributes.push(format!("style=filled, fillcolor=\"/reds9/{shade}\""));
+            }
+            match (&reachable, options.output) {
+                (Some(_), Some((output, _))) if output == node => {
+                    attributes.push("penwidth=2, peripheries=2".to_owned());
+                }
+                (Some(reachable), _) if reachable[node] => {
+                    attributes.push("penwidth=2".to_owned());
+                }
+                (Some(_), _) => attributes.push("color=gray, fontcolor=gray".to_owned()),
+                (None, _) => {}
+            }
+            lines.push(format!("    n{node} [{}];", attributes.join(", ")));
+        }
+
+        edges.sort_by_key(|&(_, child, _)| child);
+        for (parent, child, partial) in edges {
+            let mut attributes = Vec::new();
+            match partial {
+                Some(partial) => attributes.push(format!("label=\"{partial}\"")),
+                None => attributes.push("style=dashed".to_owned()),
+            }
+            if reachable
+                .as_ref()
+                .is_some_and(|reachable| !reachable[child])
+            {
+                attributes.push("color=gray, fontcolor=gray".to_owned());
+            }
+            lines.push(format!(
+                "    n{parent} -> n{child} [{}];",
+                attributes.join(", ")
+            ));
+        }
+        lines.push("}".to_owned());
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_dot_highlights_output() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let z = x.powi(2);
+        let _ = y + 1.0;
+        let grads = z.compute_gradients().unwrap();
+
+        let dot = tape.to_dot_with(
+            &DotOptions::new()
+                .with_label(&y, "\"y\"")
+                .with_output(&z)
+                .with_gradients(&grads),
+        );
+        let expected = r##"digraph tape {
+    node [shape=box];
+    n0 [label="#0 input\nvalue = 2\nadjoint = 4", style=filled, fillcolor="/reds9/9", penwidth=2];
+    n1 [label="#1 input\n\"y\"\nvalue = 3\nadjoint = 0", color=gray, fontcolor=gray];
+    n2 [label="#2 powi(2)\nvalue = 4\nadjoint = 1", style=filled, fillcolor="/reds9/3", penwidth=2, peripheries=2];
+    n3 [label="#3 add\nadjoint = 0", color=gray, fontcolor=gray];
+    n0 -> n2 [label="4"];
+    n1 -> n3 [label="1", color=gray, fontcolor=gray];
+}
+"##;
+        assert_eq!(dot, expected);
+    }
+
+    #[test]
+    fn test_to_dot_draws_external_records() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0_f64);
+        let outputs = tape.record_external(&[x], &[4.0], |w: &[f64]| vec![4.0 * w[0]]);
+        let _ = outputs[0] * 3.0;
+
+        let dot = tape.to_dot_with(&DotOptions::new().with_output(&outputs[0]));
+        assert!(dot.contains("    n0 -> n1 [style=dashed];\n"));
+        assert!(dot.contains("    n1 -> n2 [label=\"3\", color=gray, fontcolor=gray];\n"));
+        assert!(dot.contains("    n0 [label=\"#0\", penwidth=2];\n"));
+    }
+}
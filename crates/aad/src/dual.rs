@@ -0,0 +1,670 @@
+use crate::FloatLike;
+use num_traits::{One, Zero};
+use std::cmp::Ordering;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Clone, Copy, Debug)]
+/// A forward-mode dual number carrying a value and `N` tangents.
+///
+/// Evaluating a function generic over [`FloatLike`] with `Dual` arguments propagates the
+/// directional derivatives along `N` directions at once, without recording a tape.
+///
+/// # Type Parameters
+///
+/// * `F` - The underlying numeric type (typically `f64`)
+/// * `N` - The number of tangent directions
+///
+/// # Examples
+///
+/// ```
+/// use aad::Dual;
+///
+/// let [x, y] = Dual::<f64, 2>::variables(&[2.0, 3.0]);
+/// let z = x * x * y;
+/// assert_eq!(z.value(), 12.0);
+/// assert_eq!(z.tangents(), [12.0, 4.0]);
+/// ```
+pub struct Dual<F, const N: usize> {
+    value: F,
+    tangents: [F; N],
+}
+
+impl<F: Copy, const N: usize> Dual<F, N> {
+    #[inline]
+    #[must_use]
+    pub const fn new(value: F, tangents: [F; N]) -> Self {
+        Self { value, tangents }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn value(&self) -> F {
+        self.value
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn tangents(&self) -> [F; N] {
+        self.tangents
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn tangent(&self, direction: usize) -> F {
+        self.tangents[direction]
+    }
+}
+
+impl<F: Copy + Zero + One, const N: usize> Dual<F, N> {
+    #[inline]
+    #[must_use]
+    /// Creates a dual number with all tangents set to zero.
+    pub fn constant(value: F) -> Self {
+        Self::new(value, [F::zero(); N])
+    }
+
+    #[inline]
+    #[must_use]
+    /// Creates a dual number seeded with a unit tangent in `direction`.
+    pub fn variable(value: F, direction: usize) -> Self {
+        let mut tangents = [F::zero(); N];
+        tangents[direction] = F::one();
+        Self::new(value, tangents)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Creates `N` dual numbers where the `i`-th one is seeded in direction `i`.
+    pub fn variables(values: &[F; N]) -> [Self; N] {
+        std::array::from_fn(|i| Self::variable(values[i], i))
+    }
+}
+
+impl<F: Copy + Mul<Output = F>, const N: usize> Dual<F, N> {
+    #[inline]
+    /// Applies the chain rule for a unary function with the given value and derivative.
+    fn chain(self, value: F, derivative: F) -> Self {
+        Self::new(value, self.tangents.map(|t| t * derivative))
+    }
+}
+
+impl<F: Copy + Neg<Output = F>, const N: usize> Neg for Dual<F, N> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Self::new(-self.value, self.tangents.map(Neg::neg))
+    }
+}
+
+impl<F: Copy + Add<Output = F>, const N: usize> Add for Dual<F, N> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.value + rhs.value,
+            std::array::from_fn(|i| self.tangents[i] + rhs.tangents[i]),
+        )
+    }
+}
+
+impl<F: Copy + Sub<Output = F>, const N: usize> Sub for Dual<F, N> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.value - rhs.value,
+            std::array::from_fn(|i| self.tangents[i] - rhs.tangents[i]),
+        )
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl<F: Copy + Add<Output = F> + Mul<Output = F>, const N: usize> Mul for Dual<F, N> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.value * rhs.value,
+            std::array::from_fn(|i| self.tangents[i] * rhs.value + self.value * rhs.tangents[i]),
+        )
+    }
+}
+
+impl<F: Copy + Sub<Output = F> + Mul<Output = F> + Div<Output = F> + One, const N: usize> Div
+    for Dual<F, N>
+{
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        let inv = F::one() / rhs.value;
+        let value = self.value * inv;
+        Self::new(
+            value,
+            std::array::from_fn(|i| (self.tangents[i] - value * rhs.tangents[i]) * inv),
+        )
+    }
+}
+
+impl<F: Copy + Add<f64, Output = F>, const N: usize> Add<f64> for Dual<F, N> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: f64) -> Self::Output {
+        Self::new(self.value + rhs, self.tangents)
+    }
+}
+
+impl<F: Copy + Sub<f64, Output = F>, const N: usize> Sub<f64> for Dual<F, N> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: f64) -> Self::Output {
+        Self::new(self.value - rhs, self.tangents)
+    }
+}
+
+impl<F: Copy + Mul<f64, Output = F>, const N: usize> Mul<f64> for Dual<F, N> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self::new(self.value * rhs, self.tangents.map(|t| t * rhs))
+    }
+}
+
+impl<F: Copy + Div<f64, Output = F>, const N: usize> Div<f64> for Dual<F, N> {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: f64) -> Self::Output {
+        Self::new(self.value / rhs, self.tangents.map(|t| t / rhs))
+    }
+}
+
+impl<F: Copy + Add<f64, Output = F>, const N: usize> Add<Dual<F, N>> for f64 {
+    type Output = Dual<F, N>;
+
+    #[inline]
+    fn add(self, rhs: Dual<F, N>) -> Self::Output {
+        rhs + self
+    }
+}
+
+impl<F: Copy + Neg<Output = F> + Add<f64, Output = F>, const N: usize> Sub<Dual<F, N>> for f64 {
+    type Output = Dual<F, N>;
+
+    #[inline]
+    fn sub(self, rhs: Dual<F, N>) -> Self::Output {
+        -rhs + self
+    }
+}
+
+impl<F: Copy + Mul<f64, Output = F>, const N: usize> Mul<Dual<F, N>> for f64 {
+    type Output = Dual<F, N>;
+
+    #[inline]
+    fn mul(self, rhs: Dual<F, N>) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl<F, const N: usize> Div<Dual<F, N>> for f64
+where
+    F: Copy + Zero + One + Sub<Output = F> + Mul<Output = F> + Div<Output = F> + From<f64>,
+{
+    type Output = Dual<F, N>;
+
+    #[inline]
+    fn div(self, rhs: Dual<F, N>) -> Self::Output {
+        Dual::constant(F::from(self)) / rhs
+    }
+}
+
+/// Implements the by-reference, scalar and assigning variants of a binary operator for a
+/// forward-mode number type from its by-value implementations.
+macro_rules! impl_forward_op {
+    ($ty:ident, $trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident) => {
+        impl<'a, F, const N: usize> $trait<&'a $ty<F, N>> for $ty<F, N>
+        where
+            $ty<F, N>: $trait<Output = $ty<F, N>> + Copy,
+        {
+            type Output = $ty<F, N>;
+
+            #[inline]
+            fn $method(self, rhs: &'a $ty<F, N>) -> Self::Output {
+                self.$method(*rhs)
+            }
+        }
+
+        impl<F, const N: usize> $trait<$ty<F, N>> for &$ty<F, N>
+        where
+            $ty<F, N>: $trait<Output = $ty<F, N>> + Copy,
+        {
+            type Output = $ty<F, N>;
+
+            #[inline]
+            fn $method(self, rhs: $ty<F, N>) -> Self::Output {
+                (*self).$method(rhs)
+            }
+        }
+
+        impl<'a, F, const N: usize> $trait<&'a $ty<F, N>> for &$ty<F, N>
+        where
+            $ty<F, N>: $trait<Output = $ty<F, N>> + Copy,
+        {
+            type Output = $ty<F, N>;
+
+            #[inline]
+            fn $method(self, rhs: &'a $ty<F, N>) -> Self::Output {
+                (*self).$method(*rhs)
+            }
+        }
+
+        impl<'a, F, const N: usize> $trait<&'a f64> for $ty<F, N>
+        where
+            $ty<F, N>: $trait<f64, Output = $ty<F, N>>,
+        {
+            type Output = $ty<F, N>;
+
+            #[inline]
+            fn $method(self, rhs: &'a f64) -> Self::Output {
+                self.$method(*rhs)
+            }
+        }
+
+        impl<F, const N: usize> $trait<f64> for &$ty<F, N>
+        where
+            $ty<F, N>: $trait<f64, Output = $ty<F, N>> + Copy,
+        {
+            type Output = $ty<F, N>;
+
+            #[inline]
+            fn $method(self, rhs: f64) -> Self::Output {
+                (*self).$method(rhs)
+            }
+        }
+
+        impl<'a, F, const N: usize> $trait<&'a $ty<F, N>> for f64
+        where
+            f64: $trait<$ty<F, N>, Output = $ty<F, N>>,
+            $ty<F, N>: Copy,
+        {
+            type Output = $ty<F, N>;
+
+            #[inline]
+            fn $method(self, rhs: &'a $ty<F, N>) -> Self::Output {
+                self.$method(*rhs)
+            }
+        }
+
+        impl<F, const N: usize> $assign_trait for $ty<F, N>
+        where
+            $ty<F, N>: $trait<Output = $ty<F, N>> + Copy,
+        {
+            #[inline]
+            fn $assign_method(&mut self, rhs: Self) {
+                *self = (*self).$method(rhs);
+            }
+        }
+
+        impl<'a, F, const N: usize> $assign_trait<&'a $ty<F, N>> for $ty<F, N>
+        where
+            $ty<F, N>: $trait<Output = $ty<F, N>> + Copy,
+        {
+            #[inline]
+            fn $assign_method(&mut self, rhs: &'a $ty<F, N>) {
+                *self = (*self).$method(*rhs);
+            }
+        }
+
+        impl<F, const N: usize> $assign_trait<f64> for $ty<F, N>
+        where
+            $ty<F, N>: $trait<f64, Output = $ty<F, N>> + Copy,
+        {
+            #[inline]
+            fn $assign_method(&mut self, rhs: f64) {
+                *self = (*self).$method(rhs);
+            }
+        }
+
+        impl<'a, F, const N: usize> $assign_trait<&'a f64> for $ty<F, N>
+        where
+            $ty<F, N>: $trait<f64, Output = $ty<F, N>> + Copy,
+        {
+            #[inline]
+            fn $assign_method(&mut self, rhs: &'a f64) {
+                *self = (*self).$method(*rhs);
+            }
+        }
+    };
+}
+
+pub(crate) use impl_forward_op;
+
+impl_forward_op!(Dual, Add, add, AddAssign, add_assign);
+impl_forward_op!(Dual, Sub, sub, SubAssign, sub_assign);
+impl_forward_op!(Dual, Mul, mul, MulAssign, mul_assign);
+impl_forward_op!(Dual, Div, div, DivAssign, div_assign);
+
+impl<F: Copy + Zero, const N: usize> Zero for Dual<F, N> {
+    #[inline]
+    fn zero() -> Self {
+        Self::new(F::zero(), [F::zero(); N])
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.value.is_zero() && self.tangents.iter().all(Zero::is_zero)
+    }
+}
+
+impl<F: Copy + Zero + One, const N: usize> One for Dual<F, N> {
+    #[inline]
+    fn one() -> Self {
+        Self::constant(F::one())
+    }
+}
+
+impl<F: Copy + Zero, const N: usize> Sum for Dual<F, N> {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, x| acc + x)
+    }
+}
+
+impl<'a, F: Copy + Zero, const N: usize> Sum<&'a Self> for Dual<F, N> {
+    #[inline]
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, x| acc + *x)
+    }
+}
+
+impl<F: PartialEq, const N: usize> PartialEq for Dual<F, N> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<F: PartialOrd, const N: usize> PartialOrd for Dual<F, N> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<F: PartialEq<f64>, const N: usize> PartialEq<f64> for Dual<F, N> {
+    #[inline]
+    fn eq(&self, other: &f64) -> bool {
+        self.value == *other
+    }
+}
+
+impl<F: PartialOrd<f64>, const N: usize> PartialOrd<f64> for Dual<F, N> {
+    #[inline]
+    fn partial_cmp(&self, other: &f64) -> Option<Ordering> {
+        self.value.partial_cmp(other)
+    }
+}
+
+impl<F: Copy + Zero + One + From<f64>, const N: usize> From<f64> for Dual<F, N> {
+    #[inline]
+    fn from(value: f64) -> Self {
+        Self::constant(F::from(value))
+    }
+}
+
+impl<F: Into<f64>, const N: usize> From<Dual<F, N>> for f64 {
+    #[inline]
+    fn from(value: Dual<F, N>) -> Self {
+        value.value.into()
+    }
+}
+
+impl<F: FloatLike<f64>, const N: usize> FloatLike<f64> for Dual<F, N> {
+    #[inline]
+    fn sin(self) -> Self {
+        self.chain(self.value.sin(), self.value.cos())
+    }
+
+    #[inline]
+    fn cos(self) -> Self {
+        self.chain(self.value.cos(), -self.value.sin())
+    }
+
+    #[inline]
+    fn tan(self) -> Self {
+        self.chain(self.value.tan(), self.value.cos().powi(2).recip())
+    }
+
+    #[inline]
+    fn sinh(self) -> Self {
+        self.chain(self.value.sinh(), self.value.cosh())
+    }
+
+    #[inline]
+    fn cosh(self) -> Self {
+        self.chain(self.value.cosh(), self.value.sinh())
+    }
+
+    #[inline]
+    fn tanh(self) -> Self {
+        self.chain(self.value.tanh(), self.value.cosh().powi(2).recip())
+    }
+
+    #[inline]
+    fn ln(self) -> Self {
+        self.chain(self.value.ln(), self.value.recip())
+    }
+
+    #[inline]
+    fn log(self, base: f64) -> Self {
+        self.chain(self.value.log(base), self.value.recip() * base.ln().recip())
+    }
+
+    #[inline]
+    fn log2(self) -> Self {
+        self.chain(
+            self.value.log2(),
+            self.value.recip() * std::f64::consts::LN_2.recip(),
+        )
+    }
+
+    #[inline]
+    fn log10(self) -> Self {
+        self.chain(
+            self.value.log10(),
+            self.value.recip() * std::f64::consts::LN_10.recip(),
+        )
+    }
+
+    #[inline]
+    fn exp(self) -> Self {
+        let value = self.value.exp();
+        self.chain(value, value)
+    }
+
+    #[inline]
+    fn exp2(self) -> Self {
+        let value = self.value.exp2();
+        self.chain(value, value * std::f64::consts::LN_2)
+    }
+
+    #[inline]
+    fn powf(self, exponent: f64) -> Self {
+        self.chain(
+            self.value.powf(exponent),
+            self.value.powf(exponent - 1.0) * exponent,
+        )
+    }
+
+    #[inline]
+    fn powi(self, exponent: i32) -> Self {
+        self.chain(
+            self.value.powi(exponent),
+            self.value.powi(exponent - 1) * f64::from(exponent),
+        )
+    }
+
+    #[inline]
+    fn sqrt(self) -> Self {
+        let value = self.value.sqrt();
+        self.chain(value, value.recip() * 0.5)
+    }
+
+    #[inline]
+    fn cbrt(self) -> Self {
+        let value = self.value.cbrt();
+        self.chain(value, value.powi(2).recip() / 3.0)
+    }
+
+    #[inline]
+    fn recip(self) -> Self {
+        let value = self.value.recip();
+        self.chain(value, -value * value)
+    }
+
+    #[inline]
+    fn abs(self) -> Self {
+        if self.value < 0.0 { -self } else { self }
+    }
+
+    #[inline]
+    fn asin(self) -> Self {
+        self.chain(
+            self.value.asin(),
+            (F::one() - self.value * self.value).sqrt().recip(),
+        )
+    }
+
+    #[inline]
+    fn acos(self) -> Self {
+        self.chain(
+            self.value.acos(),
+            -(F::one() - self.value * self.value).sqrt().recip(),
+        )
+    }
+
+    #[inline]
+    fn atan(self) -> Self {
+        self.chain(
+            self.value.atan(),
+            (F::one() + self.value * self.value).recip(),
+        )
+    }
+
+    #[inline]
+    fn asinh(self) -> Self {
+        self.chain(
+            self.value.asinh(),
+            (self.value * self.value + F::one()).sqrt().recip(),
+        )
+    }
+
+    #[inline]
+    fn acosh(self) -> Self {
+        self.chain(
+            self.value.acosh(),
+            (self.value * self.value - F::one()).sqrt().recip(),
+        )
+    }
+
+    #[inline]
+    fn atanh(self) -> Self {
+        self.chain(
+            self.value.atanh(),
+            (F::one() - self.value * self.value).recip(),
+        )
+    }
+
+    #[inline]
+    fn hypot(self, other: Self) -> Self {
+        let value = self.value.hypot(other.value);
+        let (dx, dy) = (self.value / value, other.value / value);
+        Self::new(
+            value,
+            std::array::from_fn(|i| self.tangents[i] * dx + other.tangents[i] * dy),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tape;
+
+    const EPSILON: f64 = 1e-12;
+
+    fn assert_close<const N: usize>(actual: [f64; N], expected: [f64; N]) {
+        for (a, e) in actual.iter().zip(&expected) {
+            assert!((a - e).abs() < EPSILON, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let [x, y] = Dual::<f64, 2>::variables(&[2.0, 3.0]);
+        let z = (x + y) * x / y - x + 1.0 / x;
+        assert!((z.value() - (5.0 * 2.0 / 3.0 - 2.0 + 0.5)).abs() < EPSILON);
+        assert_close(
+            z.tangents(),
+            [(2.0 * 2.0 + 3.0) / 3.0 - 1.0 - 0.25, -4.0 / 9.0],
+        );
+
+        let mut w = x;
+        w += y;
+        w *= 2.0;
+        w -= &x;
+        w /= y;
+        assert_close(w.tangents(), [1.0 / 3.0, 2.0 / 3.0 - 8.0 / 9.0]);
+    }
+
+    #[test]
+    fn test_functions_match_tape() {
+        fn f<T: FloatLike<f64>>(x: T, y: T) -> T {
+            x.sin() * y.exp() + x.powf(2.5) / y.sqrt() + (x * y).ln() - x.hypot(y)
+                + (x / 4.0).atanh()
+                + y.cbrt() * x.tanh()
+                + x.log(3.0)
+                + y.exp2().log10()
+        }
+
+        let values = [0.7, 1.3];
+        let [x, y] = Dual::<f64, 2>::variables(&values);
+        let dual = f(x, y);
+
+        let tape = Tape::new();
+        let [vx, vy] = tape.create_variables(&values);
+        let variable = f(vx, vy);
+        let grads = variable.compute_gradients().unwrap();
+
+        assert!((dual.value() - variable.value()).abs() < EPSILON);
+        assert_close(dual.tangents(), grads.get_gradients(&[vx, vy]).unwrap());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_abs() {
+        let [x] = Dual::<f64, 1>::variables(&[-2.0]);
+        assert_eq!(x.abs().value(), 2.0);
+        assert_eq!(x.abs().tangents(), [-1.0]);
+        assert_eq!((-x).abs().tangents(), [-1.0]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_sum_and_constants() {
+        let xs = Dual::<f64, 3>::variables(&[1.0, 2.0, 3.0]);
+        let total: Dual<f64, 3> = xs.iter().sum();
+        assert_eq!(total.value(), 6.0);
+        assert_eq!(total.tangents(), [1.0, 1.0, 1.0]);
+        assert_eq!(Dual::<f64, 3>::from(2.0).tangents(), [0.0; 3]);
+        assert_eq!(f64::from(total), 6.0);
+    }
+}
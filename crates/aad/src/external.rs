@@ -0,0 +1,49 @@
+use std::fmt;
+
+type AdjointFn<F> = Box<dyn Fn(&[F]) -> Vec<F> + Send>;
+
+/// A black-box routine recorded with [`Tape::record_external`](crate::tape::Tape::record_external).
+///
+/// Its outputs occupy the consecutive nodes `first..first + outputs` and have no recorded
+/// parents; the adjoint callback links them back to `inputs` during a sweep.
+pub(crate) struct ExternalRecord<F> {
+    pub first: usize,
+    pub outputs: usize,
+    /// Tape indices of the inputs, with `usize::MAX` for constants.
+    pub inputs: Vec<usize>,
+    adjoint: AdjointFn<F>,
+}
+
+impl<F> ExternalRecord<F> {
+    #[inline]
+    pub fn new(first: usize, outputs: usize, inputs: Vec<usize>, adjoint: AdjointFn<F>) -> Self {
+        Self {
+            first,
+            outputs,
+            inputs,
+            adjoint,
+        }
+    }
+
+    #[inline]
+    /// Maps the adjoints of the outputs to the adjoints of the inputs.
+    pub fn adjoint(&self, output_adjoints: &[F]) -> Vec<F> {
+        let input_adjoints = (self.adjoint)(output_adjoints);
+        assert_eq!(
+            input_adjoints.len(),
+            self.inputs.len(),
+            "external adjoint must return one adjoint per input"
+        );
+        input_adjoints
+    }
+}
+
+impl<F> fmt::Debug for ExternalRecord<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalRecord")
+            .field("first", &self.first)
+            .field("outputs", &self.outputs)
+            .field("inputs", &self.inputs)
+            .finish_non_exhaustive()
+    }
+}
@@ -107,4 +107,54 @@ pub trait FloatLike<Scalar>:
 
     #[must_use]
     fn hypot(self, other: Self) -> Self;
+
+    #[must_use]
+    /// Evaluates `function` at `inputs`.
+    ///
+    /// Variables recorded on a tape override this to record `function` through
+    /// [`Tape::checkpoint`](crate::tape::Tape::checkpoint), keeping its intermediate operations
+    /// off the tape.
+    ///
+    /// # Panics
+    ///
+    /// The override for variables panics if `inputs` are recorded on different tapes.
+    fn checkpoint<C: Checkpoint<Scalar> + Send + 'static>(function: C, inputs: &[Self]) -> Vec<Self>
+    where
+        Scalar: Add<Self, Output = Self>
+            + Sub<Self, Output = Self>
+            + Mul<Self, Output = Self>
+            + Div<Self, Output = Self>
+            + for<'a> Add<&'a Self, Output = Self>
+            + for<'a> Sub<&'a Self, Output = Self>
+            + for<'a> Mul<&'a Self, Output = Self>
+            + for<'a> Div<&'a Self, Output = Self>,
+        for<'a> &'a Self: Add<Scalar, Output = Self>
+            + Sub<Scalar, Output = Self>
+            + Mul<Scalar, Output = Self>
+            + Div<Scalar, Output = Self>,
+    {
+        function.eval(inputs)
+    }
+}
+
+/// A function that can be evaluated for every [`FloatLike`] type, as
+/// [`FloatLike::checkpoint`] requires.
+///
+/// `#[autodiff(checkpoint)]` implements this for the function it is applied to.
+pub trait Checkpoint<Scalar> {
+    /// Evaluates the function at `inputs`.
+    fn eval<T: FloatLike<Scalar>>(&self, inputs: &[T]) -> Vec<T>
+    where
+        Scalar: Add<T, Output = T>
+            + Sub<T, Output = T>
+            + Mul<T, Output = T>
+            + Div<T, Output = T>
+            + for<'a> Add<&'a T, Output = T>
+            + for<'a> Sub<&'a T, Output = T>
+            + for<'a> Mul<&'a T, Output = T>
+            + for<'a> Div<&'a T, Output = T>,
+        for<'a> &'a T: Add<Scalar, Output = T>
+            + Sub<Scalar, Output = T>
+            + Mul<Scalar, Output = T>
+            + Div<Scalar, Output = T>;
 }
@@ -1,15 +1,51 @@
 use crate::variable::Variable;
+use num_traits::Zero;
+use std::marker::PhantomData;
 use std::mem::MaybeUninit;
 
 #[derive(Debug, PartialEq)]
 pub enum GradientError {
     MissingIndex,
     OutOfBounds(usize, usize),
+    StaleVariable(usize),
+    StorageFull(usize),
+    TapeMismatch,
+    SegmentVariable(usize),
 }
 
-pub struct Gradients<F>(pub(crate) Vec<F>);
+/// The adjoints computed by a reverse sweep, held in `S`.
+///
+/// Sweeps that allocate return `Gradients<F>`, which owns its adjoints; sweeps into a
+/// [`GradientBuffer`] return `Gradients<F, &[F]>`, which borrows them from the buffer.
+pub struct Gradients<F, S = Vec<F>> {
+    grads: S,
+    /// Address of the swept tape, or `None` if it is unknown.
+    tape: Option<usize>,
+    /// Generation of the tape when the sweep ran, or `None` if it is unknown.
+    generation: Option<usize>,
+    element: PhantomData<F>,
+}
+
+impl<F, S: AsRef<[F]>> Gradients<F, S> {
+    #[inline]
+    pub(crate) const fn new(grads: S, tape: Option<usize>, generation: Option<usize>) -> Self {
+        Self {
+            grads,
+            tape,
+            generation,
+            element: PhantomData,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the adjoints of all nodes, indexed by node.
+    pub fn as_slice(&self) -> &[F] {
+        self.grads.as_ref()
+    }
+}
 
-impl<F: Copy> Gradients<F> {
+impl<F: Copy, S: AsRef<[F]>> Gradients<F, S> {
     #[inline]
     /// Returns the gradient for the given variable.
     ///
@@ -24,14 +60,30 @@ impl<F: Copy> Gradients<F> {
     /// # Errors
     ///
     /// Returns `GradientError::MissingIndex` if the variable does not have an index
+    /// Returns `GradientError::TapeMismatch` if the variable is recorded on another tape than the swept one
     /// Returns `GradientError::OutOfBounds` if the variable's index is out of bounds
+    /// Returns `GradientError::StaleVariable` if the variable was invalidated by rewinding its tape,
+    /// or was recorded after a rewind that discarded the node the sweep saw at its index
     pub fn get_gradient(&self, x: &Variable<F>) -> Result<F, GradientError> {
-        let index = x.index.ok_or(GradientError::MissingIndex)?;
-        let idx = index.0;
-        self.0
+        let (idx, tape) = x.index.ok_or(GradientError::MissingIndex)?;
+        if self
+            .tape
+            .is_some_and(|swept| swept != std::ptr::from_ref(tape).addr())
+        {
+            return Err(GradientError::TapeMismatch);
+        }
+        if !tape.is_live(idx, x.generation)
+            || self
+                .generation
+                .is_some_and(|swept| !tape.is_live(idx, swept))
+        {
+            return Err(GradientError::StaleVariable(idx));
+        }
+        let grads = self.grads.as_ref();
+        grads
             .get(idx)
             .copied()
-            .ok_or(GradientError::OutOfBounds(idx, self.0.len()))
+            .ok_or(GradientError::OutOfBounds(idx, grads.len()))
     }
 
     #[inline]
@@ -57,7 +109,9 @@ impl<F: Copy> Gradients<F> {
     /// # Errors
     ///
     /// Returns `GradientError::MissingIndex` if any variable does not have an index
+    /// Returns `GradientError::TapeMismatch` if any variable is recorded on another tape than the swept one
     /// Returns `GradientError::OutOfBounds` if any variable's index is out of bounds
+    /// Returns `GradientError::StaleVariable` if any variable was invalidated by rewinding its tape
     pub fn get_gradients<const N: usize>(
         &self,
         vars: &[Variable<F>; N],
@@ -86,7 +140,9 @@ impl<F: Copy> Gradients<F> {
     ///
     /// Each iteration may return:
     /// * `GradientError::MissingIndex` if a variable does not have an index
+    /// * `GradientError::TapeMismatch` if a variable is recorded on another tape than the swept one
     /// * `GradientError::OutOfBounds` if a variable's index is out of bounds
+    /// * `GradientError::StaleVariable` if a variable was invalidated by rewinding its tape
     pub fn get_gradients_iter(
         &self,
         vars: &[Variable<F>],
@@ -94,3 +150,169 @@ impl<F: Copy> Gradients<F> {
         vars.iter().map(|var| self.get_gradient(var))
     }
 }
+
+/// A reusable adjoint vector for repeated reverse sweeps.
+///
+/// Sweeping into a buffer with [`Variable::compute_gradients_into`] or
+/// [`Tape::backward_into`](crate::tape::Tape::backward_into) reuses its allocation, and
+/// resets only the adjoints the previous sweep could have written.
+///
+/// # Examples
+///
+/// ```
+/// use aad::Tape;
+/// use aad::gradients::GradientBuffer;
+///
+/// let tape = Tape::new();
+/// let x = tape.create_variable(2.0_f64);
+/// let mark = tape.mark();
+/// let mut buffer = GradientBuffer::new();
+/// for k in 1..4 {
+///     let y = x.powi(k);
+///     let grads = y.compute_gradients_into(&mut buffer).unwrap();
+///     assert_eq!(grads.get_gradient(&x), Ok(f64::from(k) * 2.0_f64.powi(k - 1)));
+///     tape.rewind_to(mark);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct GradientBuffer<F> {
+    grads: Vec<F>,
+    /// Number of leading adjoints the last sweep may have written; the rest are zero.
+    dirty: usize,
+}
+
+impl<F> GradientBuffer<F> {
+    #[inline]
+    #[must_use]
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        Self {
+            grads: Vec::new(),
+            dirty: 0,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Creates a buffer with room for the adjoints of `capacity` nodes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            grads: Vec::with_capacity(capacity),
+            dirty: 0,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the number of adjoints the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.grads.capacity()
+    }
+}
+
+impl<F: Copy + Zero> GradientBuffer<F> {
+    /// Returns `len` zeroed adjoints, which the caller may write up to `dirty` of.
+    pub(crate) fn prepare(&mut self, len: usize, dirty: usize) -> &mut [F] {
+        self.grads[..self.dirty].fill(F::zero());
+        if self.grads.len() < len {
+            self.grads.resize(len, F::zero());
+        }
+        self.dirty = dirty;
+        &mut self.grads[..len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gradients::{GradientBuffer, GradientError};
+    use crate::tape::Tape;
+
+    #[test]
+    fn test_buffer_matches_compute_gradients() {
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let mark = tape.mark();
+        let mut buffer = GradientBuffer::new();
+        for k in 0..4 {
+            let mut z = x * y;
+            for _ in 0..k {
+                z = z.sin() * x + y;
+            }
+            let expected = z.compute_gradients().unwrap().get_gradients(&[x, y]);
+            let grads = z.compute_gradients_into(&mut buffer).unwrap();
+            assert_eq!(grads.get_gradients(&[x, y]), expected);
+            tape.rewind_to(mark);
+        }
+        let capacity = buffer.capacity();
+
+        let z = x * y;
+        let grads = z.compute_gradients_into(&mut buffer).unwrap();
+        assert_eq!(grads.get_gradients(&[x, y, z]), Ok([3.0, 2.0, 1.0]));
+        assert_eq!(buffer.capacity(), capacity);
+    }
+
+    #[test]
+    #[allow(clippy::many_single_char_names)]
+    fn test_buffer_resets_only_swept_adjoints() {
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let z = x * y;
+        let w = z * z;
+        let mut buffer = GradientBuffer::new();
+        let _ = w.compute_gradients_into(&mut buffer).unwrap();
+        assert_eq!(buffer.dirty, 4);
+
+        let grads = tape.backward_into(&[(x, 1.0)], &mut buffer).unwrap();
+        assert_eq!(grads.get_gradients(&[x, y, z, w]), Ok([1.0, 0.0, 0.0, 0.0]));
+        assert_eq!(buffer.dirty, 1);
+
+        let other = Tape::new();
+        let v = other.create_variable(1.0);
+        assert_eq!(
+            tape.backward_into(&[(v, 1.0)], &mut buffer).err(),
+            Some(GradientError::TapeMismatch)
+        );
+        let grads = y.compute_gradients_into(&mut buffer).unwrap();
+        assert_eq!(grads.get_gradients(&[x, y]), Ok([0.0, 1.0]));
+    }
+
+    #[test]
+    fn test_gradients_reject_nodes_recorded_after_the_sweep() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0_f64);
+        let mark = tape.mark();
+        let y = x * 5.0;
+        let grads = y.compute_gradients().unwrap();
+        tape.rewind_to(mark);
+        let z = x * 3.0;
+        assert_eq!(z.index(), y.index());
+        assert_eq!(grads.get_gradient(&x), Ok(5.0));
+        assert_eq!(grads.get_gradient(&z), Err(GradientError::StaleVariable(1)));
+
+        let grads = z.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradients(&[x, z]), Ok([3.0, 1.0]));
+    }
+
+    #[test]
+    fn test_gradients_reject_variables_of_other_tapes() {
+        let tape = Tape::new();
+        let other = Tape::new();
+        let x = tape.create_variable(2.0_f64);
+        let y = x * 5.0;
+        let u = other.create_variable(2.0_f64);
+        let v = u * 3.0;
+        assert_eq!(u.index(), x.index());
+
+        let grads = y.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradient(&x), Ok(5.0));
+        assert_eq!(grads.get_gradient(&u), Err(GradientError::TapeMismatch));
+        assert_eq!(
+            grads.get_gradients(&[x, v]),
+            Err(GradientError::TapeMismatch)
+        );
+
+        let mut buffer = GradientBuffer::new();
+        let grads = v.compute_gradients_into(&mut buffer).unwrap();
+        assert_eq!(grads.get_gradient(&x), Err(GradientError::TapeMismatch));
+    }
+}
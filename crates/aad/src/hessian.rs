@@ -0,0 +1,167 @@
+use crate::tape::Tape;
+use crate::variable::Variable;
+use num_traits::{One, Zero};
+
+/// Records `f` at `x` on a pair of nested tapes and returns the first-order gradient
+/// as variables of the outer tape.
+fn nested_gradient<'a, F, G>(f: G, x: &[Variable<'a, F>]) -> Vec<Variable<'a, F>>
+where
+    F: Copy + One + Zero,
+    G: for<'b> Fn(&[Variable<'b, Variable<'a, F>>]) -> Variable<'b, Variable<'a, F>>,
+{
+    let inner = Tape::new();
+    let inner_x = inner.create_variables_iter(x).collect::<Vec<_>>();
+    let y = f(&inner_x);
+    if y.index.is_none() {
+        return vec![Variable::zero(); x.len()];
+    }
+    let grads = y
+        .compute_gradients()
+        .expect("output of `f` is recorded on the inner tape");
+    grads
+        .get_gradients_iter(&inner_x)
+        .map(|grad| grad.expect("inputs are recorded on the inner tape"))
+        .collect()
+}
+
+#[inline]
+/// Computes the dense Hessian of a scalar function at `x`.
+///
+/// `f` is evaluated once on nested tapes, the first-order gradient is recorded on the outer
+/// tape and then differentiated in a single vector-mode reverse sweep.
+///
+/// # Arguments
+///
+/// * `f` - Function of the inputs, generic over the nested variable type
+/// * `x` - Point at which the Hessian is evaluated
+///
+/// # Returns
+///
+/// * `Vec<Vec<F>>` - Row-major Hessian where `hessian[i][j]` is `d2f/(dx_i dx_j)`
+///
+/// # Examples
+///
+/// ```
+/// use aad::hessian;
+///
+/// let h = hessian(|x| x[0] * x[0] * x[1], &[3.0_f64, 2.0]);
+/// assert_eq!(h, [[4.0, 6.0], [6.0, 0.0]]);
+/// ```
+pub fn hessian<F, G>(f: G, x: &[F]) -> Vec<Vec<F>>
+where
+    F: Copy + One + Zero,
+    G: for<'a, 'b> Fn(&[Variable<'b, Variable<'a, F>>]) -> Variable<'b, Variable<'a, F>>,
+{
+    let outer = Tape::new();
+    let outer_x = outer.create_variables_iter(x).collect::<Vec<_>>();
+    let gradient = nested_gradient(&f, &outer_x);
+
+    let recorded = gradient
+        .iter()
+        .copied()
+        .filter(|grad| grad.index.is_some())
+        .collect::<Vec<_>>();
+    let mut rows = outer
+        .jacobian(&recorded, &outer_x)
+        .expect("gradient is recorded on the outer tape")
+        .into_iter();
+    gradient
+        .iter()
+        .map(|grad| match grad.index {
+            Some(_) => rows.next().expect("one row per recorded gradient"),
+            None => vec![F::zero(); x.len()],
+        })
+        .collect()
+}
+
+#[inline]
+/// Computes the Hessian-vector product `H(x) * v` of a scalar function.
+///
+/// This costs a single reverse sweep over the outer tape, independently of the dimension.
+///
+/// # Arguments
+///
+/// * `f` - Function of the inputs, generic over the nested variable type
+/// * `x` - Point at which the Hessian is evaluated
+/// * `v` - Vector multiplied by the Hessian
+///
+/// # Returns
+///
+/// * `Vec<F>` - The product `H(x) * v`
+///
+/// # Examples
+///
+/// ```
+/// use aad::hvp;
+///
+/// let hv = hvp(|x| x[0] * x[0] * x[1], &[3.0_f64, 2.0], &[1.0, -1.0]);
+/// assert_eq!(hv, [-2.0, 6.0]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `x` and `v` have different lengths.
+pub fn hvp<F, G>(f: G, x: &[F], v: &[F]) -> Vec<F>
+where
+    F: Copy + One + Zero,
+    G: for<'a, 'b> Fn(&[Variable<'b, Variable<'a, F>>]) -> Variable<'b, Variable<'a, F>>,
+{
+    assert_eq!(x.len(), v.len(), "`x` and `v` must have the same length");
+    let outer = Tape::new();
+    let outer_x = outer.create_variables_iter(x).collect::<Vec<_>>();
+    let gradient = nested_gradient(&f, &outer_x);
+
+    let seeds = gradient
+        .into_iter()
+        .zip(v.iter().copied())
+        .filter(|(grad, _)| grad.index.is_some())
+        .collect::<Vec<_>>();
+    let grads = outer
+        .backward(&seeds)
+        .expect("gradient is recorded on the outer tape");
+    grads
+        .get_gradients_iter(&outer_x)
+        .map(|grad| grad.expect("inputs are recorded on the outer tape"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hessian() {
+        let x = [0.5, 2.0, 3.0];
+        let h = hessian(|x| x[0].sin() * x[1] + x[1] * x[1] * x[2], &x);
+        let expected = [
+            [-x[0].sin() * x[1], x[0].cos(), 0.0],
+            [x[0].cos(), 2.0 * x[2], 2.0 * x[1]],
+            [0.0, 2.0 * x[1], 0.0],
+        ];
+        assert_eq!(h, expected);
+    }
+
+    #[test]
+    fn test_hessian_of_linear_function() {
+        let h = hessian(|x| x[0] * 2.0 + x[1], &[1.0, 2.0]);
+        assert_eq!(h, [[0.0, 0.0], [0.0, 0.0]]);
+        let h = hessian(|_| Variable::constant(Variable::constant(1.0)), &[1.0]);
+        assert_eq!(h, [[0.0]]);
+    }
+
+    #[test]
+    fn test_hvp_matches_hessian() {
+        fn f<'a, 'b>(x: &[Variable<'b, Variable<'a, f64>>]) -> Variable<'b, Variable<'a, f64>> {
+            (x[0] * x[1]).exp() + x[2].powi(3) * x[0]
+        }
+
+        let x = [0.5, 2.0, 3.0];
+        let v = [1.0, -2.0, 0.5];
+        let h = hessian(f, &x);
+        let hv = hvp(f, &x, &v);
+        for (row, value) in h.iter().zip(&hv) {
+            let expected = row.iter().zip(&v).map(|(a, b)| a * b).sum::<f64>();
+            assert!((value - expected).abs() < 1e-12);
+        }
+    }
+}
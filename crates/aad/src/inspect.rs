@@ -0,0 +1,363 @@
+//! Read-only views of what a tape has recorded.
+//!
+//! These queries never modify the tape, so they can be used to audit a computation, for
+//! instance which inputs a price actually depends on, before any sweep is run.
+
+use crate::gradients::GradientError;
+use crate::replay::{OpKind, OpLog};
+use crate::tape::Tape;
+use crate::variable::Variable;
+use std::collections::BTreeMap;
+
+/// A node recorded on a tape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node<F> {
+    /// Index of the node on the tape.
+    pub index: usize,
+    /// Operation that computed the node, on tapes created with [`Tape::with_op_kinds`].
+    pub kind: Option<OpKind>,
+    /// Parents of the node, each paired with the local partial derivative towards it.
+    pub parents: Vec<(usize, F)>,
+    /// Inputs of the external routine the node is an output of, if it is one.
+    ///
+    /// Their partials are only known to the routine's adjoint callback.
+    pub external_inputs: Vec<usize>,
+}
+
+impl<F: Copy> Tape<F> {
+    #[must_use]
+    /// Returns the node recorded at `index`, or `None` if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+    /// let z = x * y;
+    /// let node = tape.node(z.index().unwrap()).unwrap();
+    /// assert_eq!(node.parents, [(0, 3.0), (1, 2.0)]);
+    /// ```
+    pub fn node(&self, index: usize) -> Option<Node<F>> {
+        (index < self.len()).then(|| self.nodes_in(index..index + 1).remove(0))
+    }
+
+    #[must_use]
+    /// Returns every node recorded on the tape, in recording order.
+    pub fn nodes(&self) -> Vec<Node<F>> {
+        self.nodes_in(0..self.len())
+    }
+
+    /// Collects the nodes in `range`.
+    fn nodes_in(&self, range: std::ops::Range<usize>) -> Vec<Node<F>> {
+        let ops = self.ops.borrow();
+        let externals = self.externals.borrow();
+        let mut nodes = Vec::with_capacity(range.len());
+        self.operations
+            .borrow()
+            .for_each(range, |index, indices, partials| {
+                let external = externals
+                    .get(
+                        externals
+                            .partition_point(|external| external.first <= index)
+                            .wrapping_sub(1),
+                    )
+                    .filter(|external| index < external.first + external.outputs);
+                nodes.push(Node {
+                    index,
+                    kind: ops.as_ref().map(|log| log.kind(index)),
+                    parents: indices
+                        .iter()
+                        .zip(partials)
+                        .map(|(&parent, &partial)| (parent as usize, partial))
+                        .collect(),
+                    external_inputs: external.map_or_else(Vec::new, |external| {
+                        external
+                            .inputs
+                            .iter()
+                            .copied()
+                            .filter(|&input| input != usize::MAX)
+                            .collect()
+                    }),
+                });
+            });
+        nodes
+    }
+
+    #[must_use]
+    /// Returns an estimate of the bytes taken by the recorded nodes.
+    ///
+    /// This counts the parents and partials of every node, the operation kinds on tapes that
+    /// record them, and the external routines, but neither spare capacity nor the captured
+    /// state of external adjoint callbacks. Nodes a storage has spilled to disk are counted
+    /// as if they were in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let x = tape.create_variable(2.0_f64);
+    /// let _ = x * x;
+    /// // Two node ends, and two parents of one index and one partial each.
+    /// assert_eq!(tape.memory_estimate(), 2 * 4 + 2 * (4 + 8));
+    /// ```
+    pub fn memory_estimate(&self) -> usize {
+        let mut parents = 0;
+        self.operations
+            .borrow()
+            .for_each(0..self.len(), |_, indices, _| parents += indices.len());
+        let externals = self
+            .externals
+            .borrow()
+            .iter()
+            .map(|external| size_of_val(external) + external.inputs.len() * size_of::<usize>())
+            .sum::<usize>();
+        let ops = self.ops.borrow().as_ref().map_or(0, OpLog::memory_estimate);
+        self.len() * size_of::<u32>()
+            + parents * (size_of::<u32>() + size_of::<F>())
+            + ops
+            + externals
+    }
+
+    #[must_use]
+    /// Counts the recorded nodes of each operation kind, by [`OpKind::name`].
+    ///
+    /// # Returns
+    ///
+    /// * `Some(BTreeMap)` - The number of nodes of each kind present on the tape
+    /// * `None` - If the tape does not record operation kinds
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new().with_op_kinds();
+    /// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+    /// let _ = (x * y).sin() * x;
+    /// let histogram = tape.op_kind_histogram().unwrap();
+    /// assert_eq!(histogram["input"], 2);
+    /// assert_eq!(histogram["mul"], 2);
+    /// assert_eq!(histogram["sin"], 1);
+    /// ```
+    pub fn op_kind_histogram(&self) -> Option<BTreeMap<&'static str, usize>> {
+        self.ops.borrow().as_ref().map(|log| {
+            let mut histogram = BTreeMap::new();
+            for index in 0..self.len() {
+                *histogram.entry(log.kind(index).name()).or_insert(0) += 1;
+            }
+            histogram
+        })
+    }
+
+    /// Returns whether `output` depends on `input` through the recorded operations.
+    ///
+    /// A node depends on itself. Dependencies through an external routine are assumed on
+    /// every input it was given.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if a variable has no index in the computation graph
+    /// * Returns `GradientError::TapeMismatch` if a variable is recorded on another tape
+    /// * Returns `GradientError::StaleVariable` if a variable was invalidated by rewinding the tape
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+    /// let z = x.sin() * 2.0;
+    /// assert_eq!(tape.depends_on(&z, &x), Ok(true));
+    /// assert_eq!(tape.depends_on(&z, &y), Ok(false));
+    /// ```
+    pub fn depends_on(
+        &self,
+        output: &Variable<'_, F>,
+        input: &Variable<'_, F>,
+    ) -> Result<bool, GradientError> {
+        let output = self.seed_index(output)?;
+        let input = self.seed_index(input)?;
+        Ok(self.cone(&[output]).get(input).copied().unwrap_or(false))
+    }
+
+    /// Returns the indices of the inputs `output` depends on, in increasing order.
+    ///
+    /// Inputs are the nodes without parents that are not outputs of an external routine,
+    /// i.e. the variables created with [`Tape::create_variable`] and its siblings.
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if `output` has no index in the computation graph
+    /// * Returns `GradientError::TapeMismatch` if `output` is recorded on another tape
+    /// * Returns `GradientError::StaleVariable` if `output` was invalidated by rewinding the tape
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let [spot, vol, rate] = tape.create_variables(&[100.0_f64, 0.2, 0.05]);
+    /// let price = spot * (rate * 2.0).exp();
+    /// assert_eq!(
+    ///     tape.reachable_inputs(&price),
+    ///     Ok(vec![spot.index().unwrap(), rate.index().unwrap()])
+    /// );
+    /// # let _ = vol;
+    /// ```
+    pub fn reachable_inputs(&self, output: &Variable<'_, F>) -> Result<Vec<usize>, GradientError> {
+        let output = self.seed_index(output)?;
+        let cone = self.cone(&[output]);
+        let externals = self.externals.borrow();
+        let mut externals = externals.iter().peekable();
+        let mut inputs = Vec::new();
+        self.operations
+            .borrow()
+            .for_each(0..cone.len(), |index, indices, _| {
+                while externals
+                    .next_if(|external| external.first + external.outputs <= index)
+                    .is_some()
+                {}
+                let external = externals
+                    .peek()
+                    .is_some_and(|external| external.first <= index);
+                if cone[index] && indices.is_empty() && !external {
+                    inputs.push(index);
+                }
+            });
+        Ok(inputs)
+    }
+
+    /// Marks the nodes that the nodes at `roots` depend on, themselves included.
+    ///
+    /// The result covers the tape up to the last root; `roots` must be recorded indices.
+    pub(crate) fn cone(&self, roots: &[usize]) -> Vec<bool> {
+        let end = roots.iter().max().map_or(0, |&root| root + 1);
+        let mut cone = vec![false; end];
+        for &root in roots {
+            cone[root] = true;
+        }
+        let externals = self.externals.borrow();
+        let mut externals = externals
+            .iter()
+            .rev()
+            .skip_while(|external| external.first >= end)
+            .peekable();
+        self.operations
+            .borrow()
+            .for_each_rev(0..end, |i, indices, _| {
+                if let Some(external) = externals.next_if(|external| external.first == i) {
+                    let outputs = i..end.min(i + external.outputs);
+                    if cone[outputs].iter().any(|&reached| reached) {
+                        for &input in external.inputs.iter().filter(|&&input| input != usize::MAX) {
+                            cone[input] = true;
+                        }
+                    }
+                }
+                if cone[i] {
+                    for &index in indices {
+                        cone[index as usize] = true;
+                    }
+                }
+            });
+        cone
+    }
+
+    /// Marks the nodes that depend on any of the nodes at `leaves`, themselves included.
+    ///
+    /// The result covers the whole tape; `leaves` must be recorded indices.
+    pub(crate) fn dependents(&self, leaves: &[usize]) -> Vec<bool> {
+        let mut reached = vec![false; self.len()];
+        for &leaf in leaves {
+            reached[leaf] = true;
+        }
+        let externals = self.externals.borrow();
+        let mut externals = externals.iter().peekable();
+        let mut external_reached = false;
+        self.operations
+            .borrow()
+            .for_each(0..reached.len(), |i, indices, _| {
+                while externals
+                    .next_if(|external| external.first + external.outputs <= i)
+                    .is_some()
+                {}
+                if let Some(external) = externals.peek().filter(|external| external.first <= i) {
+                    if external.first == i {
+                        external_reached = external
+                            .inputs
+                            .iter()
+                            .any(|&input| input != usize::MAX && reached[input]);
+                    }
+                    reached[i] |= external_reached;
+                }
+                reached[i] |= indices.iter().any(|&index| reached[index as usize]);
+            });
+        reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nodes() {
+        let tape = Tape::new().with_op_kinds();
+        let x = tape.create_variable(2.0);
+        let outputs =
+            tape.record_external(&[x, Variable::constant(1.0)], &[4.0, 5.0], |w: &[f64]| {
+                vec![w[0] + w[1], 0.0]
+            });
+        let y = outputs[1] * x;
+
+        assert_eq!(tape.nodes().len(), tape.len());
+        assert_eq!(
+            tape.node(2),
+            Some(Node {
+                index: 2,
+                kind: Some(OpKind::Opaque),
+                parents: Vec::new(),
+                external_inputs: vec![0],
+            })
+        );
+        assert_eq!(
+            tape.node(y.index().unwrap()),
+            Some(Node {
+                index: 3,
+                kind: Some(OpKind::Mul),
+                parents: vec![(2, 2.0), (0, 5.0)],
+                external_inputs: Vec::new(),
+            })
+        );
+        assert_eq!(tape.node(0).unwrap().external_inputs, Vec::<usize>::new());
+        assert_eq!(tape.node(4), None);
+        assert_eq!(Tape::<f64>::new().op_kind_histogram(), None);
+    }
+
+    #[test]
+    #[allow(clippy::many_single_char_names)]
+    fn test_dependencies() {
+        let tape = Tape::new();
+        let [a, b, c, d] = tape.create_variables(&[1.0, 2.0, 3.0, 4.0]);
+        let e = tape.record_external(&[b], &[2.0], |w: &[f64]| vec![w[0]])[0];
+        let f = a + e;
+        let g = c * d;
+
+        assert_eq!(tape.reachable_inputs(&f), Ok(vec![0, 1]));
+        assert_eq!(tape.reachable_inputs(&g), Ok(vec![2, 3]));
+        assert_eq!(tape.reachable_inputs(&e), Ok(vec![1]));
+        assert_eq!(tape.reachable_inputs(&a), Ok(vec![0]));
+        assert_eq!(tape.depends_on(&f, &e), Ok(true));
+        assert_eq!(tape.depends_on(&f, &g), Ok(false));
+        assert_eq!(tape.depends_on(&g, &f), Ok(false));
+        assert_eq!(
+            tape.depends_on(&f, &Variable::constant(1.0)),
+            Err(GradientError::MissingIndex)
+        );
+    }
+}
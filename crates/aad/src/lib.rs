@@ -1,14 +1,27 @@
+pub mod dot;
+pub mod dual;
+pub(crate) mod external;
 pub mod float_like;
 pub mod gradients;
+pub mod hessian;
+pub mod inspect;
 pub(crate) mod operation_record;
 mod overload;
+pub mod replay;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod storage;
 pub mod tape;
+pub mod taylor;
 pub mod variable;
 
 #[cfg(feature = "derive")]
 pub use aad_derive::autodiff;
+pub use dual::Dual;
 pub use float_like::FloatLike;
-pub use tape::Tape;
+pub use hessian::{hessian, hvp};
+pub use tape::{BrandedTape, Tape};
+pub use taylor::Taylor;
 pub use variable::Variable;
 
 #[cfg(test)]
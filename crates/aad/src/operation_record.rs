@@ -1,2 +1,3 @@
-#[derive(Debug)]
+/// The parents of a unary or binary node, with `usize::MAX` marking an absent parent.
+#[derive(Clone, Copy, Debug)]
 pub(crate) struct OperationRecord<F: Sized>(pub [(usize, F); 2]);
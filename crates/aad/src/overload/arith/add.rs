@@ -2,6 +2,7 @@ use std::ops::{Add, AddAssign};
 
 use num_traits::One;
 
+use crate::replay::OpKind;
 use crate::{Tape, Variable, operation_record::OperationRecord};
 
 impl<'a, F: Add<F, Output = F> + One + Copy> Add<Self> for &Variable<'a, F> {
@@ -10,29 +11,30 @@ impl<'a, F: Add<F, Output = F> + One + Copy> Add<Self> for &Variable<'a, F> {
     #[inline]
     fn add(self, rhs: Self) -> Self::Output {
         #[inline]
-        fn create_index<F: Add<F, Output = F> + One>(idx: [usize; 2], tape: &Tape<F>) -> usize {
-            let operations = &mut tape.operations.borrow_mut();
-            let count = (*operations).len();
-            (*operations).push(OperationRecord([(idx[0], F::one()), (idx[1], F::one())]));
-            count
+        fn record<F: One + Copy>(
+            value: F,
+            idx: [usize; 2],
+            constant: Option<(usize, F)>,
+            tape: &Tape<F>,
+        ) -> Variable<'_, F> {
+            tape.record(
+                value,
+                OperationRecord([(idx[0], F::one()), (idx[1], F::one())]),
+                OpKind::Add,
+                constant,
+            )
         }
 
         let value = self.value + rhs.value;
 
-        match (self.index, rhs.index) {
-            (Some((i, tape)), Some((j, _))) => Variable {
-                index: Some((create_index([i, j], tape), tape)),
-                value,
-            },
-            (None, None) => Variable { index: None, value },
-            (None, Some((j, tape))) => Variable {
-                index: Some((create_index([usize::MAX, j], tape), tape)),
-                value,
-            },
-            (Some((i, tape)), None) => Variable {
-                index: Some((create_index([i, usize::MAX], tape), tape)),
-                value,
-            },
+        match (self.live_index(), rhs.live_index()) {
+            (Some((i, tape)), Some((j, other))) => {
+                tape.debug_assert_same(other);
+                record(value, [i, j], None, tape)
+            }
+            (None, None) => Variable::constant(value),
+            (None, Some((j, tape))) => record(value, [usize::MAX, j], Some((0, self.value)), tape),
+            (Some((i, tape)), None) => record(value, [i, usize::MAX], Some((1, rhs.value)), tape),
         }
     }
 }
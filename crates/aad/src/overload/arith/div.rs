@@ -3,6 +3,7 @@ use std::ops::{Div, DivAssign, Mul, Neg};
 use num_traits::Inv;
 
 use crate::Variable;
+use crate::replay::OpKind;
 
 impl<'a, F: Copy + Div<F, Output = F> + Inv<Output = F> + Neg<Output = F> + Mul<Output = F>>
     Div<Self> for &Variable<'a, F>
@@ -11,7 +12,12 @@ impl<'a, F: Copy + Div<F, Output = F> + Inv<Output = F> + Neg<Output = F> + Mul<
 
     #[inline]
     fn div(self, rhs: Self) -> Self::Output {
-        self.apply_binary_function(rhs, |x, y| x / y, |x, y| (y.inv(), -x / (y * y)))
+        self.binary_op(
+            rhs,
+            OpKind::Div,
+            |x, y| x / y,
+            |x, y| (y.inv(), -x / (y * y)),
+        )
     }
 }
 
@@ -1,5 +1,6 @@
 use std::ops::{Mul, MulAssign};
 
+use crate::replay::OpKind;
 use crate::{Tape, Variable, operation_record::OperationRecord};
 
 impl<'a, F: Mul<F, Output = F> + Copy> Mul<Self> for &Variable<'a, F> {
@@ -8,40 +9,40 @@ impl<'a, F: Mul<F, Output = F> + Copy> Mul<Self> for &Variable<'a, F> {
     #[inline]
     fn mul(self, rhs: Self) -> Self::Output {
         #[inline]
-        fn create_index<F: Mul<F, Output = F> + Copy>(
+        fn record<F: Copy>(
             value: F,
-            rhs: F,
+            partials: [F; 2],
             idx: [usize; 2],
+            constant: Option<(usize, F)>,
             tape: &Tape<F>,
-        ) -> usize {
-            let operations = &mut tape.operations.borrow_mut();
-            let count = (*operations).len();
-            (*operations).push(OperationRecord([(idx[0], rhs), (idx[1], value)]));
-            count
+        ) -> Variable<'_, F> {
+            tape.record(
+                value,
+                OperationRecord([(idx[0], partials[0]), (idx[1], partials[1])]),
+                OpKind::Mul,
+                constant,
+            )
         }
 
         let value = self.value * rhs.value;
+        let partials = [rhs.value, self.value];
 
-        match (self.index, rhs.index) {
-            (Some((i, tape)), Some((j, _))) => Variable {
-                index: Some((create_index(self.value, rhs.value, [i, j], tape), tape)),
-                value,
-            },
-            (None, None) => Variable { index: None, value },
-            (None, Some((j, tape))) => Variable {
-                index: Some((
-                    create_index(self.value, rhs.value, [usize::MAX, j], tape),
-                    tape,
-                )),
-                value,
-            },
-            (Some((i, tape)), None) => Variable {
-                index: Some((
-                    create_index(self.value, rhs.value, [i, usize::MAX], tape),
-                    tape,
-                )),
+        match (self.live_index(), rhs.live_index()) {
+            (Some((i, tape)), Some((j, other))) => {
+                tape.debug_assert_same(other);
+                record(value, partials, [i, j], None, tape)
+            }
+            (None, None) => Variable::constant(value),
+            (None, Some((j, tape))) => record(
                 value,
-            },
+                partials,
+                [usize::MAX, j],
+                Some((0, self.value)),
+                tape,
+            ),
+            (Some((i, tape)), None) => {
+                record(value, partials, [i, usize::MAX], Some((1, rhs.value)), tape)
+            }
         }
     }
 }
@@ -2,6 +2,7 @@ use std::ops::Neg;
 
 use num_traits::{One, Zero};
 
+use crate::replay::OpKind;
 use crate::{Variable, operation_record::OperationRecord};
 
 impl<'a, F: Neg<Output = F> + One + Zero + Copy> Neg for &Variable<'a, F> {
@@ -9,20 +10,14 @@ impl<'a, F: Neg<Output = F> + One + Zero + Copy> Neg for &Variable<'a, F> {
     #[inline]
     fn neg(self) -> Self::Output {
         let value = self.value.neg();
-        match self.index {
-            Some((i, tape)) => Variable {
-                index: {
-                    let operations = &mut tape.operations.borrow_mut();
-                    let count = (*operations).len();
-                    (*operations).push(OperationRecord([
-                        (i, F::one().neg()),
-                        (usize::MAX, F::zero()),
-                    ]));
-                    Some((count, tape))
-                },
+        match self.live_index() {
+            Some((i, tape)) => tape.record(
                 value,
-            },
-            None => Variable { index: None, value },
+                OperationRecord([(i, F::one().neg()), (usize::MAX, F::zero())]),
+                OpKind::Neg,
+                None,
+            ),
+            None => Variable::constant(value),
         }
     }
 }
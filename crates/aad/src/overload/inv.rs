@@ -1,3 +1,4 @@
+use crate::replay::OpKind;
 use crate::variable::Variable;
 use num_traits::{Inv, Zero};
 use std::ops::{Mul, Neg};
@@ -9,6 +10,6 @@ impl<'a, F: Copy + Zero + Inv<Output = F> + Mul<Output = F> + Neg<Output = F>> I
 
     #[inline]
     fn inv(self) -> Self::Output {
-        self.apply_unary_function(F::inv, |x| x.mul(x).inv().neg())
+        self.unary_op(OpKind::Inv, F::inv, |x| x.mul(x).inv().neg())
     }
 }
@@ -1,3 +1,5 @@
+use crate::float_like::Checkpoint;
+use crate::replay::OpKind;
 use crate::{FloatLike, variable::Variable};
 
 impl From<Variable<'_, f64>> for f64 {
@@ -16,91 +18,111 @@ impl<F: FloatLike<f64>> Variable<'_, F> {
     #[inline]
     #[must_use]
     pub fn sin(self) -> Self {
-        self.apply_unary_function(F::sin, F::cos)
+        self.unary_op(OpKind::Sin, F::sin, F::cos)
     }
 
     #[inline]
     #[must_use]
     pub fn cos(self) -> Self {
-        self.apply_unary_function(F::cos, |x| -x.sin())
+        self.unary_op(OpKind::Cos, F::cos, |x| -x.sin())
     }
 
     #[inline]
     #[must_use]
     pub fn tan(self) -> Self {
-        self.apply_unary_function(F::tan, |x| x.cos().powi(2).recip())
+        self.unary_op(OpKind::Tan, F::tan, |x| x.cos().powi(2).recip())
     }
 
     #[inline]
     #[must_use]
     pub fn ln(self) -> Self {
-        self.apply_unary_function(F::ln, F::recip)
+        self.unary_op(OpKind::Ln, F::ln, F::recip)
     }
 
     #[inline]
     #[must_use]
     pub fn log(self, base: f64) -> Self {
-        self.apply_scalar_function(F::log, |x, b| x.recip().mul(b.ln().recip()), base)
+        self.unary_op(
+            OpKind::Log(base),
+            |x| x.log(base),
+            |x| x.recip().mul(base.ln().recip()),
+        )
     }
 
     #[inline]
     #[must_use]
     pub fn powf(self, power: f64) -> Self {
-        self.apply_scalar_function(F::powf, |x, p| x.powf(p - 1.0).mul(p), power)
+        self.unary_op(
+            OpKind::Powf(power),
+            |x| x.powf(power),
+            |x| x.powf(power - 1.0).mul(power),
+        )
     }
 
     #[inline]
     #[must_use]
     pub fn powi(self, power: i32) -> Self {
-        self.apply_scalar_function(F::powi, |x, p| F::from(f64::from(p)) * x.powi(p - 1), power)
+        self.unary_op(
+            OpKind::Powi(power),
+            |x| x.powi(power),
+            |x| F::from(f64::from(power)) * x.powi(power - 1),
+        )
     }
 
     #[inline]
     #[must_use]
     pub fn exp(self) -> Self {
-        self.apply_unary_function(F::exp, F::exp)
+        self.unary_op(OpKind::Exp, F::exp, F::exp)
     }
 
     #[inline]
     #[must_use]
     pub fn sqrt(self) -> Self {
-        self.apply_unary_function(F::sqrt, |x| x.sqrt().recip().div(F::one() + F::one()))
+        self.unary_op(OpKind::Sqrt, F::sqrt, |x| {
+            x.sqrt().recip().div(F::one() + F::one())
+        })
     }
 
     #[inline]
     #[must_use]
     pub fn cbrt(self) -> Self {
-        self.apply_unary_function(F::cbrt, |x| x.powf(-2.0 / 3.0) / 3.0)
+        self.unary_op(OpKind::Cbrt, F::cbrt, |x| x.powf(-2.0 / 3.0) / 3.0)
     }
 
     #[inline]
     #[must_use]
     pub fn recip(self) -> Self {
-        self.apply_unary_function(F::recip, |x| x.powi(2).recip().neg())
+        self.unary_op(OpKind::Recip, F::recip, |x| x.powi(2).recip().neg())
     }
 
     #[inline]
     #[must_use]
     pub fn exp2(self) -> Self {
-        self.apply_unary_function(F::exp2, |x| F::ln(F::one() + F::one()) * F::exp2(x))
+        self.unary_op(OpKind::Exp2, F::exp2, |x| {
+            F::ln(F::one() + F::one()) * F::exp2(x)
+        })
     }
 
     #[inline]
     #[must_use]
     pub fn log2(self) -> Self {
-        self.apply_unary_function(F::log2, |x| x.recip() * F::ln(F::one() + F::one()).recip())
+        self.unary_op(OpKind::Log2, F::log2, |x| {
+            x.recip() * F::ln(F::one() + F::one()).recip()
+        })
     }
 
     #[inline]
     #[must_use]
     pub fn log10(self) -> Self {
-        self.apply_unary_function(F::log10, |x| x.recip() * 10.0_f64.ln().recip())
+        self.unary_op(OpKind::Log10, F::log10, |x| {
+            x.recip() * 10.0_f64.ln().recip()
+        })
     }
 
     #[inline]
     #[must_use]
     pub fn hypot(self, other: Self) -> Self {
-        self.apply_binary_function(&other, F::hypot, |x, y| {
+        self.binary_op(&other, OpKind::Hypot, F::hypot, |x, y| {
             let denom = x.hypot(y);
             (x / denom, y / denom)
         })
@@ -116,60 +138,66 @@ impl<F: FloatLike<f64>> Variable<'_, F> {
     #[inline]
     #[must_use]
     pub fn sinh(self) -> Self {
-        self.apply_unary_function(F::sinh, F::cosh)
+        self.unary_op(OpKind::Sinh, F::sinh, F::cosh)
     }
 
     #[inline]
     #[must_use]
     pub fn cosh(self) -> Self {
-        self.apply_unary_function(F::cosh, F::sinh)
+        self.unary_op(OpKind::Cosh, F::cosh, F::sinh)
     }
 
     #[inline]
     #[must_use]
     pub fn tanh(self) -> Self {
-        self.apply_unary_function(F::tanh, |x| x.cosh().powi(2).recip())
+        self.unary_op(OpKind::Tanh, F::tanh, |x| x.cosh().powi(2).recip())
     }
 
     #[inline]
     #[must_use]
     pub fn asin(self) -> Self {
-        self.apply_unary_function(F::asin, |x| (F::one() - x * x).sqrt().recip())
+        self.unary_op(OpKind::Asin, F::asin, |x| (F::one() - x * x).sqrt().recip())
     }
 
     #[inline]
     #[must_use]
     pub fn acos(self) -> Self {
-        self.apply_unary_function(F::acos, |x| -(F::one() - x * x).sqrt().recip())
+        self.unary_op(OpKind::Acos, F::acos, |x| {
+            -(F::one() - x * x).sqrt().recip()
+        })
     }
 
     #[inline]
     #[must_use]
     pub fn atan(self) -> Self {
-        self.apply_unary_function(F::atan, |x| (F::one() + x * x).recip())
+        self.unary_op(OpKind::Atan, F::atan, |x| (F::one() + x * x).recip())
     }
 
     #[inline]
     #[must_use]
     pub fn asinh(self) -> Self {
-        self.apply_unary_function(F::asinh, |x| (x * x + F::one()).sqrt().recip())
+        self.unary_op(OpKind::Asinh, F::asinh, |x| {
+            (x * x + F::one()).sqrt().recip()
+        })
     }
 
     #[inline]
     #[must_use]
     pub fn acosh(self) -> Self {
-        self.apply_unary_function(F::acosh, |x| (x * x - F::one()).sqrt().recip())
+        self.unary_op(OpKind::Acosh, F::acosh, |x| {
+            (x * x - F::one()).sqrt().recip()
+        })
     }
 
     #[inline]
     #[must_use]
     pub fn atanh(self) -> Self {
-        self.apply_unary_function(F::atanh, |x| (F::one() - x * x).recip())
+        self.unary_op(OpKind::Atanh, F::atanh, |x| (F::one() - x * x).recip())
     }
 }
 
 macro_rules! impl_float_like {
-    ($t:ty, $scalar:ty) => {
+    ($t:ty, $scalar:ty $(, $checkpoint:item)?) => {
         impl FloatLike<$scalar> for $t {
             #[inline]
             fn sin(self) -> Self {
@@ -271,11 +299,31 @@ macro_rules! impl_float_like {
             fn hypot(self, other: Self) -> Self {
                 self.hypot(other)
             }
+            $($checkpoint)?
         }
     };
 }
 
 impl_float_like!(f32, f32);
 impl_float_like!(f64, f64);
-impl_float_like!(Variable<'_, f64>, f64);
+impl_float_like!(
+    Variable<'_, f64>,
+    f64,
+    fn checkpoint<C: Checkpoint<f64> + Send + 'static>(function: C, inputs: &[Self]) -> Vec<Self> {
+        let mut first: Option<&Self> = None;
+        for input in inputs {
+            if input.live_index().is_some() {
+                let first = *first.get_or_insert(input);
+                assert!(
+                    first.check_same_tape(input).is_ok(),
+                    "cannot combine variables recorded on different tapes"
+                );
+            }
+        }
+        match first.and_then(|first| first.index) {
+            Some((_, tape)) => tape.checkpoint(inputs, move |inputs| function.eval(inputs)),
+            None => function.eval(inputs),
+        }
+    }
+);
 impl_float_like!(Variable<'_, Variable<'_, f64>>, f64);
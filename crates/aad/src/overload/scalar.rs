@@ -9,6 +9,7 @@ mod sub;
 
 use crate::Variable;
 use crate::operation_record::OperationRecord;
+use crate::replay::OpKind;
 use num_traits::{Inv, One, Zero};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
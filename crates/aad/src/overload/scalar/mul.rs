@@ -4,17 +4,14 @@ macro_rules! impl_scalar_mul_inner {
         #[inline]
         fn mul(self, rhs: $scalar) -> Self::Output {
             let value = &self.value * rhs;
-            match &self.index {
-                Some((i, tape)) => Variable {
-                    index: {
-                        let mut operations = tape.operations.borrow_mut();
-                        let count = operations.len();
-                        operations.push(OperationRecord([(*i, rhs), (usize::MAX, $zero)]));
-                        Some((count, tape))
-                    },
+            match &self.live_index() {
+                Some((i, tape)) => tape.record(
                     value,
-                },
-                None => Variable { index: None, value },
+                    OperationRecord([(*i, rhs), (usize::MAX, $zero)]),
+                    OpKind::Mul,
+                    Some((1, rhs)),
+                ),
+                None => Variable::constant(value),
             }
         }
     };
@@ -39,20 +36,17 @@ macro_rules! impl_scalar_mul {
             #[inline]
             fn mul(self, rhs: $scalar) -> Self::Output {
                 let value = &self.value * rhs;
-                match &self.index {
-                    Some((i, tape)) => Variable {
-                        index: {
-                            let mut operations = tape.operations.borrow_mut();
-                            let count = operations.len();
-                            operations.push(OperationRecord([
-                                (*i, Variable::constant(rhs)),
-                                (usize::MAX, Variable::zero()),
-                            ]));
-                            Some((count, tape))
-                        },
+                match &self.live_index() {
+                    Some((i, tape)) => tape.record(
                         value,
-                    },
-                    None => Variable { index: None, value },
+                        OperationRecord([
+                            (*i, Variable::constant(rhs)),
+                            (usize::MAX, Variable::zero()),
+                        ]),
+                        OpKind::Mul,
+                        Some((1, Variable::constant(rhs))),
+                    ),
+                    None => Variable::constant(value),
                 }
             }
         }
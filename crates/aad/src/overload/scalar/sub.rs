@@ -1,20 +1,17 @@
 #[macro_export(local_inner_macros)]
 macro_rules! impl_scalar_sub_inner {
-    ($scalar:ty, $one:expr, $zero:expr) => {
+    ($scalar:ty, $one:expr, $zero:expr, $lift:expr) => {
         #[inline]
         fn sub(self, rhs: $scalar) -> Self::Output {
             let value = &self.value - rhs;
-            match &self.index {
-                Some((i, tape)) => Variable {
-                    index: {
-                        let mut operations = tape.operations.borrow_mut();
-                        let count = operations.len();
-                        operations.push(OperationRecord([(*i, $one), (usize::MAX, $zero)]));
-                        Some((count, tape))
-                    },
+            match &self.live_index() {
+                Some((i, tape)) => tape.record(
                     value,
-                },
-                None => Variable { index: None, value },
+                    OperationRecord([(*i, $one), (usize::MAX, $zero)]),
+                    OpKind::Sub,
+                    Some((1, $lift(rhs))),
+                ),
+                None => Variable::constant(value),
             }
         }
     };
@@ -28,7 +25,12 @@ macro_rules! impl_scalar_sub {
             for<'b> &'b $scalar: Sub<$scalar, Output = $scalar>,
         {
             type Output = Variable<'a, $scalar>;
-            impl_scalar_sub_inner!($scalar, <$scalar>::one(), <$scalar>::zero());
+            impl_scalar_sub_inner!(
+                $scalar,
+                <$scalar>::one(),
+                <$scalar>::zero(),
+                std::convert::identity
+            );
         }
 
         impl<'a, 'b> Sub<$scalar> for &Variable<'a, Variable<'b, $scalar>>
@@ -36,7 +38,12 @@ macro_rules! impl_scalar_sub {
             for<'c> &'c $scalar: Sub<$scalar, Output = $scalar>,
         {
             type Output = Variable<'a, Variable<'b, $scalar>>;
-            impl_scalar_sub_inner!($scalar, Variable::one(), Variable::zero());
+            impl_scalar_sub_inner!(
+                $scalar,
+                Variable::one(),
+                Variable::zero(),
+                Variable::constant
+            );
         }
 
         impl<'a> Sub<&Variable<'a, $scalar>> for $scalar
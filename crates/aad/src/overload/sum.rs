@@ -1,26 +1,127 @@
+use crate::replay::OpKind;
 use crate::variable::Variable;
-use num_traits::Zero;
-use std::iter::Sum;
-use std::ops::Add;
-
-impl<'a, 'b, F> Sum<&'a Variable<'b, F>> for Variable<'b, F>
-where
-    for<'c> &'c Variable<'b, F>: Add<&'c Variable<'b, F>, Output = Variable<'b, F>>,
-    Variable<'b, F>: Zero,
-{
+use num_traits::{One, Zero};
+use std::iter::{Product, Sum};
+use std::ops::Mul;
+
+/// Records `value` as a single node whose parents are the recorded variables among `terms`,
+/// each paired with its partial derivative. Returns a constant if no term is recorded.
+///
+/// The values of unrecorded terms are kept as the constant operands of `kind`.
+fn record_terms<'a, F: Copy + Zero>(
+    value: F,
+    kind: OpKind,
+    terms: impl IntoIterator<Item = (Variable<'a, F>, F)>,
+) -> Variable<'a, F> {
+    let mut tape = None;
+    let mut parents = Vec::new();
+    let mut constants = Vec::new();
+    for (position, (var, partial)) in terms.into_iter().enumerate() {
+        if let Some((idx, other)) = var.live_index() {
+            tape.get_or_insert(other).debug_assert_same(other);
+            parents.push((idx, partial));
+        } else {
+            constants.push((position, var.value));
+        }
+    }
+    match tape {
+        Some(tape) => tape.record_nary(value, &parents, kind, &constants),
+        None => Variable::constant(value),
+    }
+}
+
+impl<'a, 'b, F: Copy + One + Zero> Sum<&'a Variable<'b, F>> for Variable<'b, F> {
     #[inline]
     fn sum<I: Iterator<Item = &'a Variable<'b, F>>>(iter: I) -> Self {
-        iter.fold(Variable::zero(), |acc, x| &acc + x)
+        iter.copied().sum()
     }
 }
 
-impl<'a, F> Sum<Variable<'a, F>> for Variable<'a, F>
-where
-    Variable<'a, F>: Zero,
-{
+impl<'a, F: Copy + One + Zero> Sum<Variable<'a, F>> for Variable<'a, F> {
     #[inline]
+    /// Records the whole sum as a single node with a unit partial per recorded term.
     fn sum<I: Iterator<Item = Variable<'a, F>>>(iter: I) -> Self {
-        iter.fold(Variable::zero(), |acc, x| acc + x)
+        let terms = iter.collect::<Vec<_>>();
+        let value = terms.iter().fold(F::zero(), |acc, x| acc + x.value);
+        record_terms(value, OpKind::Sum, terms.into_iter().map(|x| (x, F::one())))
+    }
+}
+
+impl<'a, 'b, F: Copy + One + Zero> Product<&'a Variable<'b, F>> for Variable<'b, F> {
+    #[inline]
+    fn product<I: Iterator<Item = &'a Variable<'b, F>>>(iter: I) -> Self {
+        iter.copied().product()
+    }
+}
+
+impl<'a, F: Copy + One + Zero> Product<Variable<'a, F>> for Variable<'a, F> {
+    #[inline]
+    /// Records the whole product as a single node.
+    ///
+    /// The partial for each factor is the product of all other factors, computed from prefix
+    /// and suffix products so that zero factors are handled without division.
+    fn product<I: Iterator<Item = Variable<'a, F>>>(iter: I) -> Self {
+        let factors = iter.collect::<Vec<_>>();
+        let mut suffix = vec![F::one(); factors.len() + 1];
+        for (i, x) in factors.iter().enumerate().rev() {
+            suffix[i] = x.value * suffix[i + 1];
+        }
+        let mut prefix = F::one();
+        let terms = factors.iter().zip(&suffix[1..]).map(|(x, after)| {
+            let partial = prefix * *after;
+            prefix = prefix * x.value;
+            (*x, partial)
+        });
+        record_terms(suffix[0], OpKind::Product, terms)
+    }
+}
+
+impl<F: Copy + Zero + Mul<Output = F>> Variable<'_, F> {
+    #[inline]
+    #[must_use]
+    /// Computes the dot product of `lhs` and `rhs`, recorded as a single node.
+    ///
+    /// # Arguments
+    ///
+    /// * `lhs` - Left-hand vector
+    /// * `rhs` - Right-hand vector of the same length
+    ///
+    /// # Returns
+    ///
+    /// * `Variable<F>` - `sum_i lhs[i] * rhs[i]`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::{Tape, Variable};
+    ///
+    /// let tape = Tape::new();
+    /// let x = tape.create_variables(&[1.0_f64, 2.0]);
+    /// let w = [Variable::constant(3.0), Variable::constant(4.0)];
+    /// let y = Variable::dot(&x, &w);
+    /// assert_eq!(y.value(), 11.0);
+    /// let grads = y.compute_gradients().unwrap();
+    /// assert_eq!(grads.get_gradients(&x).unwrap(), [3.0, 4.0]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lhs` and `rhs` have different lengths.
+    pub fn dot(lhs: &[Self], rhs: &[Self]) -> Self {
+        assert_eq!(
+            lhs.len(),
+            rhs.len(),
+            "dot product of vectors of different lengths"
+        );
+        let value = lhs
+            .iter()
+            .zip(rhs)
+            .fold(F::zero(), |acc, (a, b)| acc + a.value * b.value);
+        let terms = lhs
+            .iter()
+            .zip(rhs)
+            .flat_map(|(a, b)| [(*a, b.value), (*b, a.value)]);
+        record_terms(value, OpKind::Dot, terms)
     }
 }
 
@@ -41,6 +142,75 @@ mod tests {
         assert!(std::ptr::eq(sum.index.unwrap().1, &tape));
     }
 
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_sum_records_single_node() {
+        let tape = Tape::new();
+        let variables = tape.create_variables(&[1.0, 2.0, 3.0, 4.0]);
+        let sum = variables
+            .iter()
+            .map(|x| x * 2.0)
+            .chain([Variable::constant(1.0)])
+            .sum::<Variable<f64>>();
+        assert_eq!(sum.value, 21.0);
+        assert_eq!(tape.len(), 4 + 4 + 1);
+        let grads = sum.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradients(&variables).unwrap(), [2.0; 4]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_product() {
+        let tape = Tape::new();
+        let variables = tape.create_variables(&[2.0, 0.0, 3.0]);
+        let product = variables
+            .iter()
+            .chain(&[Variable::constant(5.0)])
+            .product::<Variable<f64>>();
+        assert_eq!(product.value, 0.0);
+        assert_eq!(tape.len(), 4);
+        let grads = product.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradients(&variables).unwrap(), [0.0, 30.0, 0.0]);
+
+        let empty: Variable<f64> = [].iter().product();
+        assert_eq!(empty.value, 1.0);
+        assert!(empty.index.is_none());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_dot() {
+        let tape = Tape::new();
+        let x = tape.create_variables(&[1.0, 2.0, 3.0]);
+        let y = tape.create_variables(&[4.0, 5.0, 6.0]);
+        let z = Variable::dot(&x, &y);
+        assert_eq!(z.value, 32.0);
+        assert_eq!(tape.len(), 7);
+        let grads = z.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradients(&x).unwrap(), [4.0, 5.0, 6.0]);
+        assert_eq!(grads.get_gradients(&y).unwrap(), [1.0, 2.0, 3.0]);
+
+        let w = Variable::dot(&x, &x);
+        let grads = w.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradients(&x).unwrap(), [2.0, 4.0, 6.0]);
+        let jacobian = tape.jacobian(&[z, w], &x).unwrap();
+        assert_eq!(jacobian, [[4.0, 5.0, 6.0], [2.0, 4.0, 6.0]]);
+        assert_eq!(tape.jvp(&[(x[0], 1.0)], &[z, w]).unwrap(), [4.0, 2.0]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_nary_rewind() {
+        let tape = Tape::new();
+        let x = tape.create_variables(&[1.0, 2.0]);
+        let mark = tape.mark();
+        let _ = Variable::dot(&x, &x);
+        tape.rewind_to(mark);
+        let y = x.iter().sum::<Variable<f64>>() * x[0];
+        let grads = y.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradients(&x).unwrap(), [4.0, 1.0]);
+    }
+
     #[test]
     fn test_sum_empty() {
         let tape = Tape::new();
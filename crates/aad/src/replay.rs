@@ -0,0 +1,848 @@
+//! Re-evaluation of recorded tapes at new inputs.
+//!
+//! A tape created with [`Tape::with_op_kinds`] records what each node computes alongside its
+//! partials, together with the outcome of every comparison made on its variables. A
+//! [`RecordedFunction`] built from such a tape replays the recorded operations at new input
+//! values, recomputing values and partials without running the original code again, and
+//! reports when a comparison would now go the other way.
+
+mod codegen;
+
+use crate::FloatLike;
+use crate::gradients::GradientError;
+use crate::tape::Tape;
+use crate::variable::Variable;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
+
+/// What a recorded node computes from its operands.
+///
+/// The operands of a node are its parents, in recording order, interleaved with the
+/// constants it was computed from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpKind {
+    /// A variable created with [`Tape::create_variable`].
+    Input,
+    /// A node whose computation is unknown, such as one recorded with
+    /// [`Variable::apply_unary_function`] or [`Tape::record_external`].
+    Opaque,
+    Neg,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Inv,
+    Recip,
+    Sqrt,
+    Cbrt,
+    Exp,
+    Exp2,
+    Ln,
+    Log(f64),
+    Log2,
+    Log10,
+    Powf(f64),
+    Powi(i32),
+    Sin,
+    Cos,
+    Tan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Asin,
+    Acos,
+    Atan,
+    Asinh,
+    Acosh,
+    Atanh,
+    Hypot,
+    /// The sum of all operands.
+    Sum,
+    /// The product of all operands.
+    Product,
+    /// The dot product of the even and the odd operands.
+    Dot,
+}
+
+impl OpKind {
+    #[inline]
+    #[must_use]
+    /// Returns the name of the operation, without its parameter.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Input => "input",
+            Self::Opaque => "opaque",
+            Self::Neg => "neg",
+            Self::Add => "add",
+            Self::Sub => "sub",
+            Self::Mul => "mul",
+            Self::Div => "div",
+            Self::Inv => "inv",
+            Self::Recip => "recip",
+            Self::Sqrt => "sqrt",
+            Self::Cbrt => "cbrt",
+            Self::Exp => "exp",
+            Self::Exp2 => "exp2",
+            Self::Ln => "ln",
+            Self::Log(_) => "log",
+            Self::Log2 => "log2",
+            Self::Log10 => "log10",
+            Self::Powf(_) => "powf",
+            Self::Powi(_) => "powi",
+            Self::Sin => "sin",
+            Self::Cos => "cos",
+            Self::Tan => "tan",
+            Self::Sinh => "sinh",
+            Self::Cosh => "cosh",
+            Self::Tanh => "tanh",
+            Self::Asin => "asin",
+            Self::Acos => "acos",
+            Self::Atan => "atan",
+            Self::Asinh => "asinh",
+            Self::Acosh => "acosh",
+            Self::Atanh => "atanh",
+            Self::Hypot => "hypot",
+            Self::Sum => "sum",
+            Self::Product => "product",
+            Self::Dot => "dot",
+        }
+    }
+
+    #[inline]
+    /// Returns the value of a unary operation at `x`.
+    pub(crate) fn unary_value<F: FloatLike<f64>>(self, x: F) -> F {
+        match self {
+            Self::Neg => -x,
+            Self::Inv | Self::Recip => x.recip(),
+            Self::Sqrt => x.sqrt(),
+            Self::Cbrt => x.cbrt(),
+            Self::Exp => x.exp(),
+            Self::Exp2 => x.exp2(),
+            Self::Ln => x.ln(),
+            Self::Log(base) => x.log(base),
+            Self::Log2 => x.log2(),
+            Self::Log10 => x.log10(),
+            Self::Powf(power) => x.powf(power),
+            Self::Powi(power) => x.powi(power),
+            Self::Sin => x.sin(),
+            Self::Cos => x.cos(),
+            Self::Tan => x.tan(),
+            Self::Sinh => x.sinh(),
+            Self::Cosh => x.cosh(),
+            Self::Tanh => x.tanh(),
+            Self::Asin => x.asin(),
+            Self::Acos => x.acos(),
+            Self::Atan => x.atan(),
+            Self::Asinh => x.asinh(),
+            Self::Acosh => x.acosh(),
+            Self::Atanh => x.atanh(),
+            kind => unreachable!("{kind:?} is not a unary operation"),
+        }
+    }
+
+    #[inline]
+    /// Returns the derivative of a unary operation at `x`.
+    pub(crate) fn unary_derivative<F: FloatLike<f64>>(self, x: F) -> F {
+        let two = F::one() + F::one();
+        match self {
+            Self::Neg => -F::one(),
+            Self::Inv => -(x * x).recip(),
+            Self::Recip => -x.powi(2).recip(),
+            Self::Sqrt => x.sqrt().recip() / two,
+            Self::Cbrt => x.powf(-2.0 / 3.0) / 3.0,
+            Self::Exp => x.exp(),
+            Self::Exp2 => two.ln() * x.exp2(),
+            Self::Ln => x.recip(),
+            Self::Log(base) => x.recip() * base.ln().recip(),
+            Self::Log2 => x.recip() * two.ln().recip(),
+            Self::Log10 => x.recip() * 10.0_f64.ln().recip(),
+            Self::Powf(power) => x.powf(power - 1.0) * power,
+            Self::Powi(power) => F::from(f64::from(power)) * x.powi(power - 1),
+            Self::Sin => x.cos(),
+            Self::Cos => -x.sin(),
+            Self::Tan => x.cos().powi(2).recip(),
+            Self::Sinh => x.cosh(),
+            Self::Cosh => x.sinh(),
+            Self::Tanh => x.cosh().powi(2).recip(),
+            Self::Asin => (F::one() - x * x).sqrt().recip(),
+            Self::Acos => -(F::one() - x * x).sqrt().recip(),
+            Self::Atan => (F::one() + x * x).recip(),
+            Self::Asinh => (x * x + F::one()).sqrt().recip(),
+            Self::Acosh => (x * x - F::one()).sqrt().recip(),
+            Self::Atanh => (F::one() - x * x).recip(),
+            kind => unreachable!("{kind:?} is not a unary operation"),
+        }
+    }
+
+    /// Evaluates the operation on `args`, pushing the partial with respect to each of them.
+    ///
+    /// Uses the same formulas as recording, so replaying at the recorded point reproduces the
+    /// recorded values and partials exactly.
+    pub(crate) fn eval<F: FloatLike<f64>>(self, args: &[F], partials: &mut Vec<F>) -> F {
+        match self {
+            Self::Add => {
+                partials.extend([F::one(), F::one()]);
+                args[0] + args[1]
+            }
+            Self::Sub => {
+                partials.extend([F::one(), -F::one()]);
+                args[0] - args[1]
+            }
+            Self::Mul => {
+                partials.extend([args[1], args[0]]);
+                args[0] * args[1]
+            }
+            Self::Div => {
+                let (x, y) = (args[0], args[1]);
+                partials.extend([y.recip(), -x / (y * y)]);
+                x / y
+            }
+            Self::Hypot => {
+                let (x, y) = (args[0], args[1]);
+                let denom = x.hypot(y);
+                partials.extend([x / denom, y / denom]);
+                denom
+            }
+            Self::Sum => {
+                partials.extend(args.iter().map(|_| F::one()));
+                args.iter().fold(F::zero(), |acc, &x| acc + x)
+            }
+            Self::Product => {
+                let mut suffix = vec![F::one(); args.len() + 1];
+                for (i, &x) in args.iter().enumerate().rev() {
+                    suffix[i] = x * suffix[i + 1];
+                }
+                let mut prefix = F::one();
+                for (&x, &after) in args.iter().zip(&suffix[1..]) {
+                    partials.push(prefix * after);
+                    prefix *= x;
+                }
+                suffix[0]
+            }
+            Self::Dot => {
+                partials.extend(args.chunks_exact(2).flat_map(|pair| [pair[1], pair[0]]));
+                args.chunks_exact(2)
+                    .fold(F::zero(), |acc, pair| acc + pair[0] * pair[1])
+            }
+            Self::Input | Self::Opaque => unreachable!("{self:?} nodes cannot be evaluated"),
+            kind => {
+                partials.push(kind.unary_derivative(args[0]));
+                kind.unary_value(args[0])
+            }
+        }
+    }
+}
+
+impl fmt::Display for OpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Log(base) => write!(f, "log({base})"),
+            Self::Powf(exponent) => write!(f, "powf({exponent})"),
+            Self::Powi(exponent) => write!(f, "powi({exponent})"),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+/// One side of a recorded comparison.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Operand<F> {
+    Node(usize),
+    Constant(F),
+}
+
+/// The outcome of a recorded comparison.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum BranchTest {
+    Ordering(Option<Ordering>),
+    Eq(bool),
+}
+
+/// A comparison made while recording, which replaying must reproduce.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Branch<F> {
+    /// Number of nodes recorded when the comparison was made.
+    pub position: usize,
+    pub lhs: Operand<F>,
+    pub rhs: Operand<F>,
+    pub test: BranchTest,
+}
+
+impl<F: Copy + PartialOrd> Branch<F> {
+    /// Returns `true` if the comparison still has its recorded outcome for node `values`.
+    fn holds(&self, values: &[F]) -> bool {
+        let value = |operand| match operand {
+            Operand::Node(index) => values[index],
+            Operand::Constant(value) => value,
+        };
+        let (lhs, rhs) = (value(self.lhs), value(self.rhs));
+        match self.test {
+            BranchTest::Ordering(ordering) => lhs.partial_cmp(&rhs) == ordering,
+            BranchTest::Eq(equal) => (lhs == rhs) == equal,
+        }
+    }
+
+    fn nodes(&self) -> impl Iterator<Item = usize> {
+        [self.lhs, self.rhs]
+            .into_iter()
+            .filter_map(|operand| match operand {
+                Operand::Node(index) => Some(index),
+                Operand::Constant(_) => None,
+            })
+    }
+}
+
+/// The operation kinds and comparisons recorded by a tape created with
+/// [`Tape::with_op_kinds`].
+#[derive(Debug)]
+pub(crate) struct OpLog<F> {
+    kinds: Vec<OpKind>,
+    /// End offset of the constants of each node in `constants`.
+    constant_ends: Vec<u32>,
+    /// Constant operands of each node as `(position among its operands, value)`.
+    constants: Vec<(u32, F)>,
+    branches: Vec<Branch<F>>,
+}
+
+impl<F> OpLog<F> {
+    /// Creates a log for a tape that already holds `len` nodes of unknown kind.
+    pub fn new(len: usize) -> Self {
+        Self {
+            kinds: vec![OpKind::Opaque; len],
+            constant_ends: vec![0; len],
+            constants: Vec::new(),
+            branches: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn kind(&self, node: usize) -> OpKind {
+        self.kinds[node]
+    }
+
+    #[inline]
+    pub fn constants(&self, node: usize) -> &[(u32, F)] {
+        &self.constants[self.constant_range(node)]
+    }
+
+    #[inline]
+    pub fn branch_count(&self) -> usize {
+        self.branches.len()
+    }
+
+    #[inline]
+    pub fn push_branch(&mut self, branch: Branch<F>) {
+        self.branches.push(branch);
+    }
+
+    #[cfg(feature = "serde")]
+    #[inline]
+    pub fn branches(&self) -> &[Branch<F>] {
+        &self.branches
+    }
+
+    fn constant_range(&self, node: usize) -> Range<usize> {
+        let start = node
+            .checked_sub(1)
+            .map_or(0, |previous| self.constant_ends[previous]);
+        start as usize..self.constant_ends[node] as usize
+    }
+
+    /// Returns the number of bytes the log's entries take, not counting spare capacity.
+    pub fn memory_estimate(&self) -> usize {
+        self.kinds.len() * size_of::<OpKind>()
+            + self.constant_ends.len() * size_of::<u32>()
+            + self.constants.len() * size_of::<(u32, F)>()
+            + self.branches.len() * size_of::<Branch<F>>()
+    }
+
+    /// Drops the nodes at or after `len` and the comparisons after the first `branches`.
+    pub fn truncate(&mut self, len: usize, branches: usize) {
+        if len < self.kinds.len() {
+            let end = self.constant_range(len).start;
+            self.kinds.truncate(len);
+            self.constant_ends.truncate(len);
+            self.constants.truncate(end);
+        }
+        self.branches.truncate(branches);
+    }
+}
+
+impl<F: Copy> OpLog<F> {
+    // Reached from the forcibly inlined `Tape::push`; inlining it there as well bloats every
+    // overloaded operator and slows recording down even on tapes that do not log kinds.
+    #[inline(never)]
+    pub fn push(&mut self, kind: OpKind, constants: &[(usize, F)]) {
+        self.kinds.push(kind);
+        self.constants.extend(
+            constants
+                .iter()
+                .map(|&(position, value)| (crate::storage::to_u32(position), value)),
+        );
+        self.constant_ends
+            .push(crate::storage::to_u32(self.constants.len()));
+    }
+}
+
+/// Errors raised while building or replaying a [`RecordedFunction`].
+#[derive(Debug, PartialEq)]
+pub enum ReplayError {
+    /// The tape was not created with [`Tape::with_op_kinds`].
+    KindsNotRecorded,
+    /// A variable cannot be used as an input or output.
+    Variable(GradientError),
+    /// The node at this index was recorded without a replayable operation kind.
+    Opaque(usize),
+    /// The node at this index was not created with [`Tape::create_variable`].
+    NotAnInput(usize),
+    /// The expected and the given number of input values differ.
+    InputCount(usize, usize),
+    /// The comparison made after this many nodes were recorded has another outcome.
+    BranchChanged(usize),
+}
+
+impl From<GradientError> for ReplayError {
+    #[inline]
+    fn from(error: GradientError) -> Self {
+        Self::Variable(error)
+    }
+}
+
+/// A function recorded once on a tape and replayed at new input values.
+///
+/// Built from a tape created with [`Tape::with_op_kinds`], it keeps its own copy of the
+/// nodes reaching the outputs and the recorded comparisons, so the tape can be rewound or
+/// dropped afterwards.
+///
+/// # Examples
+///
+/// ```
+/// use aad::Tape;
+/// use aad::replay::{RecordedFunction, ReplayError};
+///
+/// let tape = Tape::new().with_op_kinds();
+/// let [x, y] = tape.create_variables(&[1.0_f64, 2.0]);
+/// let z = if x < y { (x * y).sin() } else { x.powf(2.5) };
+///
+/// let mut f = RecordedFunction::new(&tape, &[x, y], &[z]).unwrap();
+/// assert_eq!(f.evaluate(&[0.5, 3.0]), Ok(vec![1.5_f64.sin()]));
+/// assert_eq!(f.gradient(0), [3.0 * 1.5_f64.cos(), 0.5 * 1.5_f64.cos()]);
+/// assert_eq!(f.evaluate(&[3.0, 0.5]), Err(ReplayError::BranchChanged(2)));
+/// ```
+#[derive(Debug)]
+pub struct RecordedFunction<F> {
+    ends: Vec<u32>,
+    indices: Vec<u32>,
+    partials: Vec<F>,
+    kinds: Vec<OpKind>,
+    constant_ends: Vec<u32>,
+    constants: Vec<(u32, F)>,
+    branches: Vec<Branch<F>>,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+    /// Every node created with [`Tape::create_variable`], with its recorded value.
+    leaves: Vec<(usize, F)>,
+    values: Vec<F>,
+}
+
+impl<F: FloatLike<f64>> RecordedFunction<F> {
+    /// Copies the part of `tape` that `outputs` depend on, to be replayed at new values of
+    /// `inputs`.
+    ///
+    /// Variables created on the tape but not listed in `inputs` keep their recorded values.
+    /// Every comparison recorded on the tape that depends on `inputs` is checked on replay,
+    /// including those made after the outputs were computed, so the nodes they compare are
+    /// copied as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `tape` - Tape created with [`Tape::with_op_kinds`]
+    /// * `inputs` - Variables created with [`Tape::create_variable`] whose values can change
+    /// * `outputs` - Variables whose values and gradients are computed
+    ///
+    /// # Errors
+    ///
+    /// * Returns `ReplayError::KindsNotRecorded` if the tape does not record operation kinds
+    /// * Returns `ReplayError::Variable` if an input or output cannot be used on the tape
+    /// * Returns `ReplayError::NotAnInput` if an input was not created as a variable
+    /// * Returns `ReplayError::Opaque` if a node the outputs or a checked comparison depend on
+    ///   was recorded without a replayable kind
+    pub fn new(
+        tape: &Tape<F>,
+        inputs: &[Variable<'_, F>],
+        outputs: &[Variable<'_, F>],
+    ) -> Result<Self, ReplayError> {
+        let inputs = inputs
+            .iter()
+            .map(|input| tape.seed_index(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = outputs
+            .iter()
+            .map(|output| tape.seed_index(output))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ops = tape.ops.borrow();
+        let log = ops.as_ref().ok_or(ReplayError::KindsNotRecorded)?;
+
+        // Comparisons of nodes that no input reaches hold at every point, and are dropped.
+        let varying = tape.dependents(&inputs);
+        let branches = log
+            .branches
+            .iter()
+            .filter(|branch| branch.nodes().any(|node| varying[node]))
+            .copied()
+            .collect::<Vec<_>>();
+        let roots = outputs
+            .iter()
+            .copied()
+            .chain(branches.iter().flat_map(Branch::nodes))
+            .collect::<Vec<_>>();
+        let mut keep = tape.cone(&roots);
+        let len = inputs
+            .iter()
+            .map(|&input| input + 1)
+            .fold(keep.len(), usize::max);
+        keep.resize(len, false);
+        if let Some(node) = (0..len).find(|&node| keep[node] && log.kind(node) == OpKind::Opaque) {
+            return Err(ReplayError::Opaque(node));
+        }
+        if let Some(&input) = inputs
+            .iter()
+            .find(|&&input| log.kind(input) != OpKind::Input)
+        {
+            return Err(ReplayError::NotAnInput(input));
+        }
+        for &input in &inputs {
+            keep[input] = true;
+        }
+
+        // Kept nodes are renumbered in recording order.
+        let mut renumbered = vec![usize::MAX; len];
+        let mut kinds = Vec::new();
+        let mut constant_ends = Vec::new();
+        let mut constants = Vec::new();
+        let mut leaves = Vec::new();
+        for node in (0..len).filter(|&node| keep[node]) {
+            renumbered[node] = kinds.len();
+            let kind = log.kind(node);
+            if kind == OpKind::Input {
+                leaves.push((kinds.len(), log.constants(node)[0].1));
+            }
+            kinds.push(kind);
+            constants.extend_from_slice(log.constants(node));
+            constant_ends.push(crate::storage::to_u32(constants.len()));
+        }
+        let mut ends = Vec::with_capacity(kinds.len());
+        let mut indices = Vec::new();
+        let mut partials = Vec::new();
+        tape.operations
+            .borrow()
+            .for_each(0..len, |node, parents, node_partials| {
+                if keep[node] {
+                    indices.extend(
+                        parents
+                            .iter()
+                            .map(|&parent| crate::storage::to_u32(renumbered[parent as usize])),
+                    );
+                    partials.extend_from_slice(node_partials);
+                    ends.push(crate::storage::to_u32(indices.len()));
+                }
+            });
+        let operand = |operand| match operand {
+            Operand::Node(node) => Operand::Node(renumbered[node]),
+            constant @ Operand::Constant(_) => constant,
+        };
+        let branches = branches
+            .into_iter()
+            .map(|branch| Branch {
+                lhs: operand(branch.lhs),
+                rhs: operand(branch.rhs),
+                ..branch
+            })
+            .collect();
+        let inputs = inputs.iter().map(|&input| renumbered[input]).collect();
+        let outputs = outputs.iter().map(|&output| renumbered[output]).collect();
+        let values = vec![F::zero(); kinds.len()];
+
+        Ok(Self {
+            ends,
+            indices,
+            partials,
+            kinds,
+            constant_ends,
+            constants,
+            branches,
+            inputs,
+            outputs,
+            leaves,
+            values,
+        })
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the number of recorded nodes.
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns `true` if no nodes are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    /// Replays the recorded operations at new input values.
+    ///
+    /// Values and partials of every node are recomputed, so that [`RecordedFunction::gradient`]
+    /// afterwards returns the gradients at the new point.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - One value per input, in the order given to [`RecordedFunction::new`]
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<F>)` - The values of the outputs
+    /// * `Err(ReplayError)` - If the inputs do not fit or a recorded branch no longer holds
+    ///
+    /// # Errors
+    ///
+    /// * Returns `ReplayError::InputCount` if `inputs` does not hold one value per input
+    /// * Returns `ReplayError::BranchChanged` if a recorded comparison has another outcome at
+    ///   the new point, in which case the recording does not describe the function there
+    pub fn evaluate(&mut self, inputs: &[F]) -> Result<Vec<F>, ReplayError> {
+        if inputs.len() != self.inputs.len() {
+            return Err(ReplayError::InputCount(self.inputs.len(), inputs.len()));
+        }
+        for &(node, value) in &self.leaves {
+            self.values[node] = value;
+        }
+        for (&node, &value) in self.inputs.iter().zip(inputs) {
+            self.values[node] = value;
+        }
+
+        let mut args = Vec::new();
+        let mut local = Vec::new();
+        let mut start = 0;
+        let mut constant_start = 0;
+        for node in 0..self.len() {
+            let end = self.ends[node] as usize;
+            let constant_end = self.constant_ends[node] as usize;
+            let kind = self.kinds[node];
+            if kind != OpKind::Input {
+                let parents = &self.indices[start..end];
+                let constants = &self.constants[constant_start..constant_end];
+                args.clear();
+                let mut constant = constants.iter().peekable();
+                let mut parent = parents.iter();
+                for position in 0..parents.len() + constants.len() {
+                    if let Some(&(_, value)) = constant.next_if(|&&(at, _)| at as usize == position)
+                    {
+                        args.push(value);
+                    } else {
+                        let index = *parent.next().expect("operands match parents");
+                        args.push(self.values[index as usize]);
+                    }
+                }
+                local.clear();
+                self.values[node] = kind.eval(&args, &mut local);
+                let mut constant = constants.iter().peekable();
+                let mut slot = start;
+                for (position, &partial) in local.iter().enumerate() {
+                    if constant
+                        .next_if(|&&(at, _)| at as usize == position)
+                        .is_none()
+                    {
+                        self.partials[slot] = partial;
+                        slot += 1;
+                    }
+                }
+            }
+            start = end;
+            constant_start = constant_end;
+        }
+
+        if let Some(branch) = self.branches.iter().find(|b| !b.holds(&self.values)) {
+            return Err(ReplayError::BranchChanged(branch.position));
+        }
+        Ok(self
+            .outputs
+            .iter()
+            .map(|&output| self.values[output])
+            .collect())
+    }
+
+    #[must_use]
+    /// Returns the derivatives of output `output` with respect to every input, at the point
+    /// last passed to [`RecordedFunction::evaluate`] or else the recorded one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` is not less than the number of outputs.
+    pub fn gradient(&self, output: usize) -> Vec<F> {
+        let mut adjoints = vec![F::zero(); self.len()];
+        adjoints[self.outputs[output]] = F::one();
+        for node in (0..self.len()).rev() {
+            let adjoint = adjoints[node];
+            if adjoint.is_zero() {
+                continue;
+            }
+            let start = node
+                .checked_sub(1)
+                .map_or(0, |previous| self.ends[previous]);
+            let parents = start as usize..self.ends[node] as usize;
+            for (&idx, &partial) in self.indices[parents.clone()]
+                .iter()
+                .zip(&self.partials[parents])
+            {
+                let idx = idx as usize;
+                adjoints[idx] += partial * adjoint;
+            }
+        }
+        self.inputs.iter().map(|&input| adjoints[input]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(clippy::many_single_char_names)]
+    fn record<'a>(
+        tape: &'a Tape<f64>,
+        x: Variable<'a, f64>,
+        y: Variable<'a, f64>,
+    ) -> Variable<'a, f64> {
+        let c = tape.create_variable(0.25);
+        let u = (2.0 * x + y / 3.0).exp() - x.powi(3) * y.ln();
+        let v = [x, y, Variable::constant(1.5)]
+            .into_iter()
+            .product::<Variable<_>>();
+        let w = Variable::dot(&[x, u], &[Variable::constant(0.5), v]);
+        let z = [
+            w.hypot(x),
+            (4.0 - y).sqrt(),
+            x.recip().atan(),
+            c,
+            Variable::constant(-0.5),
+        ]
+        .into_iter()
+        .sum::<Variable<_>>();
+        if z > 0.0 { z.tanh() } else { z.cosh() }
+    }
+
+    #[test]
+    #[allow(clippy::many_single_char_names)]
+    fn test_replay_matches_recording() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[0.7, 1.3]);
+        let z = record(&tape, x, y);
+        let mut f = RecordedFunction::new(&tape, &[x, y], &[z]).unwrap();
+
+        for point in [[0.7, 1.3], [0.4, 2.1], [1.1, 0.9]] {
+            let fresh = Tape::new();
+            let [a, b] = fresh.create_variables(&point);
+            let expected = record(&fresh, a, b);
+            let grads = expected.compute_gradients().unwrap();
+
+            assert_eq!(f.evaluate(&point), Ok(vec![expected.value()]));
+            assert_eq!(f.gradient(0), grads.get_gradients(&[a, b]).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_replay_detects_branch_change() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[1.0, 2.0]);
+        let z = if x == y { x } else { x * y };
+        let mut f = RecordedFunction::new(&tape, &[x, y], &[z]).unwrap();
+
+        assert_eq!(f.evaluate(&[3.0, 4.0]), Ok(vec![12.0]));
+        assert_eq!(f.evaluate(&[3.0, 3.0]), Err(ReplayError::BranchChanged(2)));
+    }
+
+    #[test]
+    fn test_replay_checks_comparisons_recorded_after_outputs() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[1.0, 2.0]);
+        let z = if x * y > 1.0 { y } else { x };
+        let mut f = RecordedFunction::new(&tape, &[x, y], &[z]).unwrap();
+
+        assert_eq!(f.evaluate(&[1.5, 3.0]), Ok(vec![3.0]));
+        assert_eq!(f.gradient(0), [0.0, 1.0]);
+        assert_eq!(f.evaluate(&[0.1, 0.2]), Err(ReplayError::BranchChanged(3)));
+    }
+
+    #[test]
+    fn test_replay_keeps_only_nodes_that_matter() {
+        let tape = Tape::new().with_op_kinds();
+        let x = tape.create_variable(2.0);
+        let z = x * 3.0;
+        let unrelated = tape.create_variable(5.0);
+        let _ = unrelated.apply_unary_function(|v| v * v, |v| 2.0 * v);
+        let _ = unrelated < 1.0;
+        let y = tape.create_variable(4.0);
+        let mut f = RecordedFunction::new(&tape, &[x, y], &[z]).unwrap();
+
+        assert_eq!(f.len(), 3);
+        assert_eq!(f.evaluate(&[1.0, 7.0]), Ok(vec![3.0]));
+        assert_eq!(f.gradient(0), [3.0, 0.0]);
+    }
+
+    #[test]
+    fn test_unbound_inputs_keep_recorded_values() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[1.0, 2.0]);
+        let z = x * y + 1.0;
+        let mut f = RecordedFunction::new(&tape, &[x], &[z]).unwrap();
+
+        assert_eq!(f.evaluate(&[5.0]), Ok(vec![11.0]));
+        assert_eq!(f.gradient(0), [2.0]);
+    }
+
+    #[test]
+    fn test_rewind_truncates_kinds_and_branches() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[1.0, 2.0]);
+        let mark = tape.mark();
+        let _ = (x < y, x.sin());
+        tape.rewind_to(mark);
+
+        let z = x * y;
+        let mut f = RecordedFunction::new(&tape, &[x, y], &[z]).unwrap();
+        assert_eq!(f.evaluate(&[3.0, 1.0]), Ok(vec![3.0]));
+    }
+
+    #[test]
+    fn test_new_rejects_unreplayable_tapes() {
+        let tape = Tape::new();
+        let [x] = tape.create_variables(&[1.0_f64]);
+        let y = x.sin();
+        assert_eq!(
+            RecordedFunction::new(&tape, &[x], &[y]).unwrap_err(),
+            ReplayError::KindsNotRecorded
+        );
+
+        let tape = Tape::new().with_op_kinds();
+        let [x] = tape.create_variables(&[1.0_f64]);
+        let y = x.sin();
+        let z = y.apply_unary_function(|v| v * v, |v| 2.0 * v);
+        assert_eq!(
+            RecordedFunction::new(&tape, &[x], &[z]).unwrap_err(),
+            ReplayError::Opaque(2)
+        );
+        assert_eq!(
+            RecordedFunction::new(&tape, &[y], &[y]).unwrap_err(),
+            ReplayError::NotAnInput(1)
+        );
+        assert_eq!(
+            RecordedFunction::new(&tape, &[Variable::constant(1.0)], &[y]).unwrap_err(),
+            ReplayError::Variable(GradientError::MissingIndex)
+        );
+
+        let mut f = RecordedFunction::new(&tape, &[x], &[y]).unwrap();
+        assert_eq!(f.evaluate(&[]), Err(ReplayError::InputCount(1, 0)));
+    }
+}
@@ -0,0 +1,412 @@
+use super::{Branch, BranchTest, OpKind, Operand, RecordedFunction};
+use std::cmp::Ordering;
+
+impl RecordedFunction<f64> {
+    #[must_use]
+    /// Generates a standalone Rust function computing output `output` and its gradient.
+    ///
+    /// The function takes one value per input, in the order given to [`RecordedFunction::new`],
+    /// and returns the output value together with its derivatives with respect to the inputs.
+    /// It is straight-line code over `f64` locals, without a [`Tape`](crate::Tape) or any
+    /// allocation, and uses the same formulas as recording, so it returns the same values as
+    /// [`Variable::compute_gradients`](crate::Variable::compute_gradients) on a freshly
+    /// recorded tape.
+    ///
+    /// Every recorded comparison is checked with `debug_assert!`, as the generated code only
+    /// describes the function where they keep their outcome; the nodes they compare are
+    /// computed even when the output does not depend on them.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the generated function
+    /// * `output` - Index of the output among those given to [`RecordedFunction::new`]
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Source of a function `fn name(x: &[f64; N]) -> (f64, [f64; N])`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    /// use aad::replay::RecordedFunction;
+    ///
+    /// let tape = Tape::new().with_op_kinds();
+    /// let [x, y] = tape.create_variables(&[1.0_f64, 2.0]);
+    /// let z = x * y + x.sin();
+    ///
+    /// let f = RecordedFunction::new(&tape, &[x, y], &[z]).unwrap();
+    /// let code = f.to_rust("f", 0);
+    /// assert!(code.contains("pub fn f(x: &[f64; 2]) -> (f64, [f64; 2]) {"));
+    /// assert!(code.contains("let v3 = v0.sin();"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` is not less than the number of outputs.
+    pub fn to_rust(&self, name: &str, output: usize) -> String {
+        let output = self.outputs[output];
+        let reaches = self.cone([output]);
+        let needed =
+            self.cone(std::iter::once(output).chain(self.branches.iter().flat_map(Branch::nodes)));
+
+        let mut lines = vec![
+            "// Generated by aad from a recorded tape; do not edit.".to_owned(),
+            String::new(),
+            "#[allow(clippy::all, clippy::pedantic, unused_parens, unused_variables)]".to_owned(),
+            "#[must_use]".to_owned(),
+            format!(
+                "pub fn {name}(x: &[f64; {inputs}]) -> (f64, [f64; {inputs}]) {{",
+                inputs = self.inputs.len()
+            ),
+        ];
+        for (node, kind) in self.kinds.iter().enumerate() {
+            if !needed[node] {
+                continue;
+            }
+            let value = if *kind == OpKind::Input {
+                self.input_expr(node)
+            } else {
+                value_expr(*kind, &self.operands(node))
+            };
+            lines.push(format!("    let v{node} = {value};"));
+        }
+        lines.extend(self.branch_checks());
+
+        let mut terms = vec![Vec::new(); output + 1];
+        for node in (0..=output).rev() {
+            if !reaches[node] {
+                continue;
+            }
+            let adjoint = if node == output {
+                "1.0_f64".to_owned()
+            } else {
+                terms[node].join(" + ")
+            };
+            lines.push(format!("    let a{node} = {adjoint};"));
+            if self.kinds[node] == OpKind::Input {
+                continue;
+            }
+            let partials =
+                partial_exprs(self.kinds[node], &self.operands(node), &format!("v{node}"));
+            let positions = self.constant_positions(node);
+            let mut parents = self.parents(node).iter();
+            for (position, partial) in partials.into_iter().enumerate() {
+                if positions.contains(&position) {
+                    continue;
+                }
+                let parent = *parents.next().expect("operands match parents") as usize;
+                terms[parent].push(match partial.as_str() {
+                    "1.0_f64" => format!("a{node}"),
+                    "-1.0_f64" => format!("-a{node}"),
+                    _ => format!("{partial} * a{node}"),
+                });
+            }
+        }
+
+        let gradient = self
+            .inputs
+            .iter()
+            .map(|&input| {
+                if reaches[input] {
+                    format!("a{input}")
+                } else {
+                    "0.0_f64".to_owned()
+                }
+            })
+            .collect::<Vec<_>>();
+        lines.push(format!("    (v{output}, [{}])", gradient.join(", ")));
+        lines.push("}".to_owned());
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Returns the expression of a variable created on the tape, which is read from the
+    /// arguments if it is an input and is otherwise its recorded value.
+    fn input_expr(&self, node: usize) -> String {
+        if let Some(position) = self.inputs.iter().position(|&input| input == node) {
+            format!("x[{position}]")
+        } else {
+            let &(_, value) = self
+                .leaves
+                .iter()
+                .find(|&&(leaf, _)| leaf == node)
+                .expect("input nodes are leaves");
+            literal(value)
+        }
+    }
+
+    /// Returns a `debug_assert!` for each recorded comparison.
+    fn branch_checks(&self) -> impl Iterator<Item = String> {
+        self.branches.iter().map(|branch| {
+            format!(
+                "    debug_assert!({}, \"branch recorded at node {} no longer holds\");",
+                branch_expr(
+                    branch.test,
+                    &branch_operand(branch.lhs),
+                    &branch_operand(branch.rhs)
+                ),
+                branch.position
+            )
+        })
+    }
+
+    /// Marks the nodes that the nodes at `roots` depend on, themselves included.
+    fn cone(&self, roots: impl IntoIterator<Item = usize>) -> Vec<bool> {
+        let mut cone = vec![false; self.len()];
+        for root in roots {
+            cone[root] = true;
+        }
+        for node in (0..self.len()).rev() {
+            if cone[node] {
+                for &parent in self.parents(node) {
+                    cone[parent as usize] = true;
+                }
+            }
+        }
+        cone
+    }
+
+    fn parents(&self, node: usize) -> &[u32] {
+        let start = node
+            .checked_sub(1)
+            .map_or(0, |previous| self.ends[previous]);
+        &self.indices[start as usize..self.ends[node] as usize]
+    }
+
+    fn node_constants(&self, node: usize) -> &[(u32, f64)] {
+        let start = node
+            .checked_sub(1)
+            .map_or(0, |previous| self.constant_ends[previous]);
+        &self.constants[start as usize..self.constant_ends[node] as usize]
+    }
+
+    fn constant_positions(&self, node: usize) -> Vec<usize> {
+        self.node_constants(node)
+            .iter()
+            .map(|&(position, _)| position as usize)
+            .collect()
+    }
+
+    /// Returns the operands of `node` as expressions, interleaving parents and constants.
+    fn operands(&self, node: usize) -> Vec<String> {
+        let (parents, constants) = (self.parents(node), self.node_constants(node));
+        let count = parents.len() + constants.len();
+        let mut constants = constants.iter().peekable();
+        let mut parents = parents.iter();
+        (0..count)
+            .map(|position| {
+                if let Some(&(_, value)) = constants.next_if(|&&(at, _)| at as usize == position) {
+                    operand(value)
+                } else {
+                    format!("v{}", parents.next().expect("operands match parents"))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Formats `value` as an `f64` expression that evaluates to exactly `value`.
+fn literal(value: f64) -> String {
+    if value.is_nan() {
+        "f64::NAN".to_owned()
+    } else if value == f64::INFINITY {
+        "f64::INFINITY".to_owned()
+    } else if value == f64::NEG_INFINITY {
+        "f64::NEG_INFINITY".to_owned()
+    } else {
+        format!("{value:?}_f64")
+    }
+}
+
+/// Formats `value` for use as an operand or a method receiver.
+fn operand(value: f64) -> String {
+    let literal = literal(value);
+    if literal.starts_with('-') {
+        format!("({literal})")
+    } else {
+        literal
+    }
+}
+
+/// Wraps `expr` in parentheses unless it is a single term.
+fn group(expr: &str) -> String {
+    if expr.contains(' ') {
+        format!("({expr})")
+    } else {
+        expr.to_owned()
+    }
+}
+
+fn branch_operand(side: Operand<f64>) -> String {
+    match side {
+        Operand::Node(node) => format!("v{node}"),
+        Operand::Constant(value) => operand(value),
+    }
+}
+
+fn branch_expr(test: BranchTest, lhs: &str, rhs: &str) -> String {
+    match test {
+        BranchTest::Ordering(Some(Ordering::Less)) => format!("{lhs} < {rhs}"),
+        BranchTest::Ordering(Some(Ordering::Greater)) => format!("{lhs} > {rhs}"),
+        BranchTest::Ordering(Some(Ordering::Equal)) | BranchTest::Eq(true) => {
+            format!("{lhs} == {rhs}")
+        }
+        BranchTest::Ordering(None) => format!("{lhs}.partial_cmp(&{rhs}).is_none()"),
+        BranchTest::Eq(false) => format!("{lhs} != {rhs}"),
+    }
+}
+
+/// Returns the product `args[0] * (args[1] * (...))`, associated as the recorded product is.
+fn product_suffix(args: &[String]) -> Option<String> {
+    let (last, rest) = args.split_last()?;
+    Some(
+        rest.iter()
+            .rev()
+            .fold(last.clone(), |acc, arg| format!("{arg} * {}", group(&acc))),
+    )
+}
+
+/// Returns the expression computing the value of `kind` from its operands.
+fn value_expr(kind: OpKind, a: &[String]) -> String {
+    match kind {
+        OpKind::Neg => format!("-{}", a[0]),
+        OpKind::Add => format!("{} + {}", a[0], a[1]),
+        OpKind::Sub => format!("{} - {}", a[0], a[1]),
+        OpKind::Mul => format!("{} * {}", a[0], a[1]),
+        OpKind::Div => format!("{} / {}", a[0], a[1]),
+        OpKind::Hypot => format!("{}.hypot({})", a[0], a[1]),
+        OpKind::Sum => a.join(" + "),
+        OpKind::Product => product_suffix(a).unwrap_or_else(|| "1.0_f64".to_owned()),
+        OpKind::Dot => a
+            .chunks_exact(2)
+            .map(|pair| format!("{} * {}", pair[0], pair[1]))
+            .collect::<Vec<_>>()
+            .join(" + "),
+        OpKind::Inv | OpKind::Recip => format!("{}.recip()", a[0]),
+        OpKind::Log(base) => format!("{}.log({})", a[0], literal(base)),
+        OpKind::Powf(power) => format!("{}.powf({})", a[0], literal(power)),
+        OpKind::Powi(power) => format!("{}.powi({power})", a[0]),
+        OpKind::Input | OpKind::Opaque => unreachable!("{kind:?} nodes have no expression"),
+        kind => format!("{}.{}()", a[0], kind.name()),
+    }
+}
+
+/// Returns the expressions computing the partials of `kind` with respect to its operands,
+/// given the expression of its own value.
+fn partial_exprs(kind: OpKind, a: &[String], value: &str) -> Vec<String> {
+    let one = "1.0_f64".to_owned();
+    let x = a.first().map_or("", String::as_str);
+    match kind {
+        OpKind::Add => vec![one.clone(), one],
+        OpKind::Sub => vec![one, "-1.0_f64".to_owned()],
+        OpKind::Mul => vec![a[1].clone(), a[0].clone()],
+        OpKind::Div => vec![
+            format!("{}.recip()", a[1]),
+            format!("-{} / ({} * {})", a[0], a[1], a[1]),
+        ],
+        OpKind::Hypot => vec![format!("{} / {value}", a[0]), format!("{} / {value}", a[1])],
+        OpKind::Sum => vec![one; a.len()],
+        OpKind::Product => (0..a.len())
+            .map(|i| {
+                let prefix = (i > 0).then(|| a[..i].join(" * "));
+                match (prefix, product_suffix(&a[i + 1..])) {
+                    (None, None) => one.clone(),
+                    (Some(prefix), None) => prefix,
+                    (None, Some(suffix)) => suffix,
+                    (Some(prefix), Some(suffix)) => format!("{prefix} * {}", group(&suffix)),
+                }
+            })
+            .collect(),
+        OpKind::Dot => a
+            .chunks_exact(2)
+            .flat_map(|pair| [pair[1].clone(), pair[0].clone()])
+            .collect(),
+        OpKind::Neg => vec!["-1.0_f64".to_owned()],
+        OpKind::Inv => vec![format!("-({x} * {x}).recip()")],
+        OpKind::Recip => vec![format!("-{x}.powi(2).recip()")],
+        OpKind::Sqrt => vec![format!("{x}.sqrt().recip() / 2.0_f64")],
+        OpKind::Cbrt => vec![format!("{x}.powf({}) / 3.0_f64", literal(-2.0 / 3.0))],
+        OpKind::Exp => vec![format!("{x}.exp()")],
+        OpKind::Exp2 => vec![format!("2.0_f64.ln() * {x}.exp2()")],
+        OpKind::Ln => vec![format!("{x}.recip()")],
+        OpKind::Log(base) => vec![format!("{x}.recip() * {}.ln().recip()", operand(base))],
+        OpKind::Log2 => vec![format!("{x}.recip() * 2.0_f64.ln().recip()")],
+        OpKind::Log10 => vec![format!("{x}.recip() * 10.0_f64.ln().recip()")],
+        OpKind::Powf(power) => vec![format!(
+            "{x}.powf({}) * {}",
+            literal(power - 1.0),
+            operand(power)
+        )],
+        OpKind::Powi(power) => vec![format!(
+            "{} * {x}.powi({})",
+            operand(f64::from(power)),
+            power - 1
+        )],
+        OpKind::Sin => vec![format!("{x}.cos()")],
+        OpKind::Cos => vec![format!("-{x}.sin()")],
+        OpKind::Tan => vec![format!("{x}.cos().powi(2).recip()")],
+        OpKind::Sinh => vec![format!("{x}.cosh()")],
+        OpKind::Cosh => vec![format!("{x}.sinh()")],
+        OpKind::Tanh => vec![format!("{x}.cosh().powi(2).recip()")],
+        OpKind::Asin => vec![format!("(1.0_f64 - {x} * {x}).sqrt().recip()")],
+        OpKind::Acos => vec![format!("-(1.0_f64 - {x} * {x}).sqrt().recip()")],
+        OpKind::Atan => vec![format!("(1.0_f64 + {x} * {x}).recip()")],
+        OpKind::Asinh => vec![format!("({x} * {x} + 1.0_f64).sqrt().recip()")],
+        OpKind::Acosh => vec![format!("({x} * {x} - 1.0_f64).sqrt().recip()")],
+        OpKind::Atanh => vec![format!("(1.0_f64 - {x} * {x}).recip()")],
+        OpKind::Input | OpKind::Opaque => unreachable!("{kind:?} nodes have no partials"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Tape;
+    use crate::replay::RecordedFunction;
+
+    #[test]
+    #[allow(clippy::many_single_char_names)]
+    fn test_to_rust() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y, c] = tape.create_variables(&[1.0, 2.0, -0.5]);
+        let z = if x < y { (x * y - c).sin() } else { x };
+        let f = RecordedFunction::new(&tape, &[x, y], &[z]).unwrap();
+
+        let expected = r#"// Generated by aad from a recorded tape; do not edit.
+
+#[allow(clippy::all, clippy::pedantic, unused_parens, unused_variables)]
+#[must_use]
+pub fn f(x: &[f64; 2]) -> (f64, [f64; 2]) {
+    let v0 = x[0];
+    let v1 = x[1];
+    let v2 = -0.5_f64;
+    let v3 = v0 * v1;
+    let v4 = v3 - v2;
+    let v5 = v4.sin();
+    debug_assert!(v0 < v1, "branch recorded at node 3 no longer holds");
+    let a5 = 1.0_f64;
+    let a4 = v4.cos() * a5;
+    let a3 = a4;
+    let a2 = -a4;
+    let a1 = v0 * a3;
+    let a0 = v1 * a3;
+    (v5, [a0, a1])
+}
+"#;
+        assert_eq!(f.to_rust("f", 0), expected);
+    }
+
+    #[test]
+    fn test_to_rust_checks_comparisons_outside_the_output_cone() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[1.0, 2.0]);
+        let z = if x * y > 1.0 { y } else { x };
+        let f = RecordedFunction::new(&tape, &[x, y], &[z]).unwrap();
+
+        let code = f.to_rust("f", 0);
+        assert!(code.contains(
+            "    let v2 = v0 * v1;\n    debug_assert!(v2 > 1.0_f64, \"branch recorded at node 3 no longer holds\");\n"
+        ));
+        assert!(code.contains("    (v1, [0.0_f64, a1])\n"));
+    }
+}
@@ -0,0 +1,756 @@
+//! Versioned binary format for tapes and gradients.
+//!
+//! A [`Tape`] is saved with its nodes and, if it records them, its operation kinds and
+//! comparisons; [`Gradients`] are saved with their adjoints. Values are written with
+//! [`SpillValue`], so a file records the size of the values it holds and loading it with
+//! another value type fails instead of misreading it.
+//!
+//! Node labels are not part of the format: they are given to
+//! [`DotOptions`](crate::dot::DotOptions) when a tape is exported and are not stored on it.
+//!
+//! Both types also implement `serde`'s `Serialize` and `Deserialize` as a byte string holding
+//! this format, so they can be embedded in any `serde` data format.
+//!
+//! # Examples
+//!
+//! ```
+//! use aad::Tape;
+//!
+//! let tape = Tape::new();
+//! let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+//! let z = x * y + x.sin();
+//!
+//! let mut bytes = Vec::new();
+//! tape.write_to(&mut bytes).unwrap();
+//!
+//! let loaded = Tape::<f64>::read_from(bytes.as_slice()).unwrap();
+//! let z = loaded.variable_at(loaded.len() - 1, z.value()).unwrap();
+//! let grads = z.compute_gradients().unwrap();
+//! assert_eq!(grads.as_slice()[..2], [3.0 + 2.0_f64.cos(), 2.0]);
+//! ```
+
+use crate::gradients::Gradients;
+use crate::replay::{Branch, BranchTest, OpKind, OpLog, Operand};
+use crate::storage::{SpillValue, StorageError};
+use crate::tape::Tape;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::io::{self, BufReader, Read, Write};
+use std::mem::discriminant;
+use std::{error, fmt};
+
+/// Version of the format written by this crate; files of any other version are rejected.
+pub const FORMAT_VERSION: u16 = 1;
+
+const TAPE_MAGIC: [u8; 4] = *b"AADT";
+const GRADIENTS_MAGIC: [u8; 4] = *b"AADG";
+
+/// Number of buffered bytes after which they are passed on to the writer.
+const FLUSH_THRESHOLD: usize = 1 << 16;
+
+/// Operation kinds in the order of their tags; parameters are stored after the tag.
+const KIND_TAGS: [OpKind; 35] = [
+    OpKind::Input,
+    OpKind::Opaque,
+    OpKind::Neg,
+    OpKind::Add,
+    OpKind::Sub,
+    OpKind::Mul,
+    OpKind::Div,
+    OpKind::Inv,
+    OpKind::Recip,
+    OpKind::Sqrt,
+    OpKind::Cbrt,
+    OpKind::Exp,
+    OpKind::Exp2,
+    OpKind::Ln,
+    OpKind::Log(0.0),
+    OpKind::Log2,
+    OpKind::Log10,
+    OpKind::Powf(0.0),
+    OpKind::Powi(0),
+    OpKind::Sin,
+    OpKind::Cos,
+    OpKind::Tan,
+    OpKind::Sinh,
+    OpKind::Cosh,
+    OpKind::Tanh,
+    OpKind::Asin,
+    OpKind::Acos,
+    OpKind::Atan,
+    OpKind::Asinh,
+    OpKind::Acosh,
+    OpKind::Atanh,
+    OpKind::Hypot,
+    OpKind::Sum,
+    OpKind::Product,
+    OpKind::Dot,
+];
+
+/// An error raised while saving or loading a tape or gradients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    /// The data could not be read or written.
+    Io(io::ErrorKind),
+    /// The data does not start with the magic bytes of the expected kind of file.
+    BadMagic,
+    /// The data was written in another version of the format.
+    UnsupportedVersion(u16),
+    /// The data holds values of `.0` bytes, where values of `.1` bytes were expected.
+    ValueSize(usize, usize),
+    /// The data ends before its last item.
+    UnexpectedEnd,
+    /// The data is inconsistent, for the given reason.
+    Malformed(&'static str),
+    /// The tape holds black-box routines recorded with [`Tape::record_external`], whose
+    /// adjoint callbacks cannot be saved.
+    ExternalRecord,
+    /// A loaded node could not be stored.
+    Storage(StorageError),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(kind) => write!(f, "i/o error: {kind}"),
+            Self::BadMagic => {
+                f.write_str("not a serialized tape or gradients of the expected kind")
+            }
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "format version {version} is not supported, expected version {FORMAT_VERSION}"
+            ),
+            Self::ValueSize(found, expected) => write!(
+                f,
+                "values of {found} bytes cannot be loaded as values of {expected} bytes"
+            ),
+            Self::UnexpectedEnd => f.write_str("data ends unexpectedly"),
+            Self::Malformed(reason) => write!(f, "malformed data: {reason}"),
+            Self::ExternalRecord => f.write_str("tapes with external records cannot be saved"),
+            Self::Storage(error) => write!(f, "loaded node cannot be stored: {error:?}"),
+        }
+    }
+}
+
+impl error::Error for FormatError {}
+
+impl From<io::Error> for FormatError {
+    #[inline]
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => Self::UnexpectedEnd,
+            kind => Self::Io(kind),
+        }
+    }
+}
+
+/// Buffers encoded items and passes them on to a writer in large blocks.
+struct Encoder<W> {
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Encoder<W> {
+    fn new<F: SpillValue>(writer: W, magic: [u8; 4]) -> Self {
+        let mut encoder = Self {
+            writer,
+            buffer: Vec::with_capacity(FLUSH_THRESHOLD),
+        };
+        encoder.buffer.extend_from_slice(&magic);
+        encoder
+            .buffer
+            .extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        encoder.len(F::SIZE);
+        encoder
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        self.buffer.extend_from_slice(&(len as u64).to_le_bytes());
+    }
+
+    fn value<F: SpillValue>(&mut self, value: F) {
+        value.write_bytes(&mut self.buffer);
+    }
+
+    fn flush_if_full(&mut self) -> Result<(), FormatError> {
+        if self.buffer.len() >= FLUSH_THRESHOLD {
+            self.writer.write_all(&self.buffer)?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), FormatError> {
+        self.writer.write_all(&self.buffer)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads encoded items, checking the header on creation and the end of data on completion.
+struct Decoder<R> {
+    reader: BufReader<R>,
+    scratch: Vec<u8>,
+}
+
+impl<R: Read> Decoder<R> {
+    fn new<F: SpillValue>(reader: R, magic: [u8; 4]) -> Result<Self, FormatError> {
+        let mut decoder = Self {
+            reader: BufReader::new(reader),
+            scratch: Vec::new(),
+        };
+        if decoder.bytes::<4>()? != magic {
+            return Err(FormatError::BadMagic);
+        }
+        let version = u16::from_le_bytes(decoder.bytes()?);
+        if version != FORMAT_VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        let size = decoder.len()?;
+        if size != F::SIZE {
+            return Err(FormatError::ValueSize(size, F::SIZE));
+        }
+        Ok(decoder)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], FormatError> {
+        let mut bytes = [0; N];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, FormatError> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, FormatError> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn len(&mut self) -> Result<usize, FormatError> {
+        usize::try_from(u64::from_le_bytes(self.bytes()?))
+            .map_err(|_| FormatError::Malformed("length does not fit in memory"))
+    }
+
+    fn value<F: SpillValue>(&mut self) -> Result<F, FormatError> {
+        self.scratch.resize(F::SIZE, 0);
+        self.reader.read_exact(&mut self.scratch)?;
+        Ok(F::read_bytes(&self.scratch))
+    }
+
+    fn finish(mut self) -> Result<(), FormatError> {
+        if self.reader.read(&mut [0])? == 0 {
+            Ok(())
+        } else {
+            Err(FormatError::Malformed("trailing data"))
+        }
+    }
+}
+
+fn write_kind<W: Write>(encoder: &mut Encoder<W>, kind: OpKind) {
+    let tag = KIND_TAGS
+        .iter()
+        .position(|tagged| discriminant(tagged) == discriminant(&kind))
+        .expect("every kind has a tag");
+    encoder.u8(u8::try_from(tag).expect("kind tags fit in a byte"));
+    match kind {
+        OpKind::Log(parameter) | OpKind::Powf(parameter) => encoder.value(parameter),
+        OpKind::Powi(power) => encoder.u32(power.cast_unsigned()),
+        _ => {}
+    }
+}
+
+fn read_kind<R: Read>(decoder: &mut Decoder<R>) -> Result<OpKind, FormatError> {
+    let kind = *KIND_TAGS
+        .get(usize::from(decoder.u8()?))
+        .ok_or(FormatError::Malformed("unknown operation kind"))?;
+    Ok(match kind {
+        OpKind::Log(_) => OpKind::Log(decoder.value()?),
+        OpKind::Powf(_) => OpKind::Powf(decoder.value()?),
+        OpKind::Powi(_) => OpKind::Powi(decoder.u32()?.cast_signed()),
+        kind => kind,
+    })
+}
+
+/// Returns `true` if a node of `kind` can have `parents` parents and `constants` constant
+/// operands.
+fn has_arity(kind: OpKind, parents: usize, constants: usize) -> bool {
+    let operands = parents + constants;
+    match kind {
+        OpKind::Opaque => true,
+        // Inputs hold their value as their only operand.
+        OpKind::Input => parents == 0 && constants == 1,
+        OpKind::Add | OpKind::Sub | OpKind::Mul | OpKind::Div | OpKind::Hypot => operands == 2,
+        OpKind::Sum | OpKind::Product => operands > 0,
+        OpKind::Dot => operands > 0 && operands.is_multiple_of(2),
+        _ => operands == 1,
+    }
+}
+
+fn write_operand<W: Write, F: SpillValue>(encoder: &mut Encoder<W>, operand: Operand<F>) {
+    match operand {
+        Operand::Node(node) => {
+            encoder.u8(0);
+            encoder.len(node);
+        }
+        Operand::Constant(value) => {
+            encoder.u8(1);
+            encoder.value(value);
+        }
+    }
+}
+
+fn read_operand<R: Read, F: SpillValue>(
+    decoder: &mut Decoder<R>,
+    len: usize,
+) -> Result<Operand<F>, FormatError> {
+    match decoder.u8()? {
+        0 => match decoder.len()? {
+            node if node < len => Ok(Operand::Node(node)),
+            _ => Err(FormatError::Malformed("comparison of an unknown node")),
+        },
+        1 => Ok(Operand::Constant(decoder.value()?)),
+        _ => Err(FormatError::Malformed("unknown comparison operand")),
+    }
+}
+
+fn branch_test_tag(test: BranchTest) -> u8 {
+    match test {
+        BranchTest::Ordering(Some(Ordering::Less)) => 0,
+        BranchTest::Ordering(Some(Ordering::Equal)) => 1,
+        BranchTest::Ordering(Some(Ordering::Greater)) => 2,
+        BranchTest::Ordering(None) => 3,
+        BranchTest::Eq(true) => 4,
+        BranchTest::Eq(false) => 5,
+    }
+}
+
+fn branch_test(tag: u8) -> Result<BranchTest, FormatError> {
+    Ok(match tag {
+        0 => BranchTest::Ordering(Some(Ordering::Less)),
+        1 => BranchTest::Ordering(Some(Ordering::Equal)),
+        2 => BranchTest::Ordering(Some(Ordering::Greater)),
+        3 => BranchTest::Ordering(None),
+        4 => BranchTest::Eq(true),
+        5 => BranchTest::Eq(false),
+        _ => return Err(FormatError::Malformed("unknown comparison outcome")),
+    })
+}
+
+impl<F: SpillValue> Tape<F> {
+    /// Saves the nodes of the tape and, if it records them, its operation kinds and
+    /// comparisons.
+    ///
+    /// Node labels are not saved, since they belong to the
+    /// [`DotOptions`](crate::dot::DotOptions) of an export rather than to the tape.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the data, which is buffered internally
+    ///
+    /// # Errors
+    ///
+    /// * Returns `FormatError::ExternalRecord` if the tape holds external records
+    /// * Returns `FormatError::Io` if the data cannot be written
+    pub fn write_to(&self, writer: impl Write) -> Result<(), FormatError> {
+        if !self.externals.borrow().is_empty() {
+            return Err(FormatError::ExternalRecord);
+        }
+        let mut encoder = Encoder::new::<F>(writer, TAPE_MAGIC);
+        let len = self.len();
+        encoder.len(len);
+        let mut result = Ok(());
+        self.operations
+            .borrow()
+            .for_each(0..len, |_, indices, partials| {
+                if result.is_err() {
+                    return;
+                }
+                encoder.u32(crate::storage::to_u32(indices.len()));
+                for (&index, &partial) in indices.iter().zip(partials) {
+                    encoder.u32(index);
+                    encoder.value(partial);
+                }
+                result = encoder.flush_if_full();
+            });
+        result?;
+
+        match self.ops.borrow().as_ref() {
+            None => encoder.u8(0),
+            Some(log) => {
+                encoder.u8(1);
+                for node in 0..len {
+                    write_kind(&mut encoder, log.kind(node));
+                    let constants = log.constants(node);
+                    encoder.u32(crate::storage::to_u32(constants.len()));
+                    for &(position, value) in constants {
+                        encoder.u32(position);
+                        encoder.value(value);
+                    }
+                    encoder.flush_if_full()?;
+                }
+                encoder.len(log.branch_count());
+                for branch in log.branches() {
+                    encoder.len(branch.position);
+                    write_operand(&mut encoder, branch.lhs);
+                    write_operand(&mut encoder, branch.rhs);
+                    encoder.u8(branch_test_tag(branch.test));
+                    encoder.flush_if_full()?;
+                }
+            }
+        }
+        encoder.finish()
+    }
+
+    /// Loads a tape saved with [`Tape::write_to`].
+    ///
+    /// The loaded tape keeps its nodes in memory. Its variables can be recovered with
+    /// [`Tape::variable_at`] to run reverse sweeps from them.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Source of the data, which is buffered internally
+    ///
+    /// # Errors
+    ///
+    /// * Returns `FormatError::BadMagic` if the data is not a saved tape
+    /// * Returns `FormatError::UnsupportedVersion` if the data is in another format version
+    /// * Returns `FormatError::ValueSize` if the data holds values of another size than `F`
+    /// * Returns `FormatError::UnexpectedEnd` if the data is truncated
+    /// * Returns `FormatError::Malformed` if the data is inconsistent
+    /// * Returns `FormatError::Storage` if a node cannot be stored
+    /// * Returns `FormatError::Io` if the data cannot be read
+    pub fn read_from(reader: impl Read) -> Result<Self, FormatError> {
+        let mut decoder = Decoder::new::<F>(reader, TAPE_MAGIC)?;
+        let tape = Self::new();
+        let len = decoder.len()?;
+        let mut counts = Vec::new();
+        {
+            let mut operations = tape.operations.borrow_mut();
+            let mut parents = Vec::new();
+            for node in 0..len {
+                parents.clear();
+                let count = decoder.u32()?;
+                for _ in 0..count {
+                    let index = decoder.u32()?;
+                    if index as usize >= node {
+                        return Err(FormatError::Malformed("parent recorded after its child"));
+                    }
+                    parents.push((index, decoder.value()?));
+                }
+                operations.push(&parents).map_err(FormatError::Storage)?;
+                counts.push(count as usize);
+            }
+        }
+
+        match decoder.u8()? {
+            0 => {}
+            1 => {
+                let mut log = OpLog::new(0);
+                let mut constants = Vec::new();
+                for &parents in &counts {
+                    let kind = read_kind(&mut decoder)?;
+                    constants.clear();
+                    for _ in 0..decoder.u32()? {
+                        let position = decoder.u32()? as usize;
+                        if constants.last().is_some_and(|&(last, _)| position <= last) {
+                            return Err(FormatError::Malformed("constant operands out of order"));
+                        }
+                        constants.push((position, decoder.value()?));
+                    }
+                    let operands = parents + constants.len();
+                    if !has_arity(kind, parents, constants.len())
+                        || constants.last().is_some_and(|&(last, _)| last >= operands)
+                    {
+                        return Err(FormatError::Malformed("operands do not fit operation kind"));
+                    }
+                    log.push(kind, &constants);
+                }
+                for _ in 0..decoder.len()? {
+                    let position = decoder.len()?;
+                    let lhs = read_operand(&mut decoder, len)?;
+                    let rhs = read_operand(&mut decoder, len)?;
+                    let test = branch_test(decoder.u8()?)?;
+                    if position > len
+                        || log.branches().last().is_some_and(|b| position < b.position)
+                    {
+                        return Err(FormatError::Malformed("comparison out of order"));
+                    }
+                    log.push_branch(Branch {
+                        position,
+                        lhs,
+                        rhs,
+                        test,
+                    });
+                }
+                tape.set_op_log(log);
+            }
+            _ => return Err(FormatError::Malformed("unknown operation kind flag")),
+        }
+        decoder.finish()?;
+        Ok(tape)
+    }
+}
+
+impl<F: SpillValue, S: AsRef<[F]>> Gradients<F, S> {
+    /// Saves the adjoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the data, which is buffered internally
+    ///
+    /// # Errors
+    ///
+    /// Returns `FormatError::Io` if the data cannot be written
+    pub fn write_to(&self, writer: impl Write) -> Result<(), FormatError> {
+        let mut encoder = Encoder::new::<F>(writer, GRADIENTS_MAGIC);
+        let grads = self.as_slice();
+        encoder.len(grads.len());
+        for &grad in grads {
+            encoder.value(grad);
+            encoder.flush_if_full()?;
+        }
+        encoder.finish()
+    }
+}
+
+impl<F: SpillValue> Gradients<F> {
+    /// Loads adjoints saved with [`Gradients::write_to`].
+    ///
+    /// Neither the swept tape nor its generation at the sweep is saved, so the loaded adjoints
+    /// are read for any live variable by its index alone; only variables invalidated by
+    /// rewinding their own tape are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Source of the data, which is buffered internally
+    ///
+    /// # Errors
+    ///
+    /// * Returns `FormatError::BadMagic` if the data is not saved gradients
+    /// * Returns `FormatError::UnsupportedVersion` if the data is in another format version
+    /// * Returns `FormatError::ValueSize` if the data holds values of another size than `F`
+    /// * Returns `FormatError::UnexpectedEnd` if the data is truncated
+    /// * Returns `FormatError::Malformed` if the data continues after the adjoints
+    /// * Returns `FormatError::Io` if the data cannot be read
+    pub fn read_from(reader: impl Read) -> Result<Self, FormatError> {
+        let mut decoder = Decoder::new::<F>(reader, GRADIENTS_MAGIC)?;
+        let len = decoder.len()?;
+        let grads = (0..len)
+            .map(|_| decoder.value())
+            .collect::<Result<Vec<_>, _>>()?;
+        decoder.finish()?;
+        Ok(Self::new(grads, None, None))
+    }
+}
+
+/// Accepts a byte string in any of the shapes `serde` data formats use for one.
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a byte string")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Ok(bytes.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(bytes)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+impl<F: SpillValue> Serialize for Tape<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes).map_err(ser::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+impl<'de, F: SpillValue> Deserialize<'de> for Tape<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserializer.deserialize_byte_buf(BytesVisitor)?;
+        Self::read_from(bytes.as_slice()).map_err(de::Error::custom)
+    }
+}
+
+impl<F: SpillValue, S: AsRef<[F]>> Serialize for Gradients<F, S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes).map_err(ser::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+impl<'de, F: SpillValue> Deserialize<'de> for Gradients<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserializer.deserialize_byte_buf(BytesVisitor)?;
+        Self::read_from(bytes.as_slice()).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradients::GradientError;
+    use crate::replay::{RecordedFunction, ReplayError};
+
+    fn saved(tape: &Tape<f64>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        tape.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_tape_round_trip() {
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[0.5, 1.5]);
+        let z = [x * y, (x / y).exp(), y.powi(3)]
+            .into_iter()
+            .sum::<crate::Variable<_>>();
+        let expected = z.compute_gradients().unwrap();
+
+        let loaded = Tape::<f64>::read_from(saved(&tape).as_slice()).unwrap();
+        assert_eq!(loaded.len(), tape.len());
+        assert!(!loaded.records_op_kinds());
+        let z = loaded.variable_at(tape.len() - 1, z.value()).unwrap();
+        assert_eq!(
+            z.compute_gradients().unwrap().as_slice(),
+            expected.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_tape_round_trip_keeps_op_kinds() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[1.0_f64, 2.0]);
+        let z = if x < y {
+            (x * 3.0).powf(1.5)
+        } else {
+            y.log(10.0)
+        };
+
+        let loaded = Tape::<f64>::read_from(saved(&tape).as_slice()).unwrap();
+        assert!(loaded.records_op_kinds());
+        let [x, y] = [0, 1].map(|i| loaded.variable_at(i, 0.0).unwrap());
+        let z = loaded.variable_at(z.index.unwrap().0, z.value()).unwrap();
+        let mut f = RecordedFunction::new(&loaded, &[x, y], &[z]).unwrap();
+        assert_eq!(f.evaluate(&[3.0, 4.0]), Ok(vec![9.0_f64.powf(1.5)]));
+        assert_eq!(f.evaluate(&[4.0, 3.0]), Err(ReplayError::BranchChanged(2)));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_gradients_round_trip() {
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[2.0_f32, 3.0]);
+        let grads = (x * y).compute_gradients().unwrap();
+
+        let mut bytes = Vec::new();
+        grads.write_to(&mut bytes).unwrap();
+        let loaded = Gradients::<f32>::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.get_gradients(&[x, y]).unwrap(), [3.0, 2.0]);
+    }
+
+    #[test]
+    fn test_gradients_round_trip_after_rewind() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0_f64);
+        let mark = tape.mark();
+        let _ = x * 5.0;
+        tape.rewind_to(mark);
+        let y = x * 3.0;
+        let grads = y.compute_gradients().unwrap();
+
+        let mut bytes = Vec::new();
+        grads.write_to(&mut bytes).unwrap();
+        let loaded = Gradients::<f64>::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.get_gradients(&[x, y]), Ok([3.0, 1.0]));
+
+        tape.rewind_to(mark);
+        assert_eq!(
+            loaded.get_gradient(&y),
+            Err(GradientError::StaleVariable(1))
+        );
+    }
+
+    #[test]
+    fn test_read_rejects_invalid_data() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[1.0, 2.0]);
+        let _ = x * y;
+        let bytes = saved(&tape);
+        let read = |bytes: &[u8]| Tape::<f64>::read_from(bytes).map(|tape| tape.len());
+
+        assert_eq!(
+            read(&bytes[..bytes.len() - 1]),
+            Err(FormatError::UnexpectedEnd)
+        );
+        assert_eq!(
+            read(&[bytes.as_slice(), &[0]].concat()),
+            Err(FormatError::Malformed("trailing data"))
+        );
+        assert_eq!(
+            Gradients::<f64>::read_from(bytes.as_slice()).map(|_| ()),
+            Err(FormatError::BadMagic)
+        );
+        assert_eq!(
+            Tape::<f32>::read_from(bytes.as_slice()).map(|tape| tape.len()),
+            Err(FormatError::ValueSize(8, 4))
+        );
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            read(&newer),
+            Err(FormatError::UnsupportedVersion(FORMAT_VERSION + 1))
+        );
+
+        // The third node has parents 0 and 1; make the first of them point at the node itself.
+        let mut cyclic = bytes.clone();
+        let third = 4 + 2 + 8 + 8 + 2 * 4 + 4;
+        cyclic[third..third + 4].copy_from_slice(&2_u32.to_le_bytes());
+        assert_eq!(
+            read(&cyclic),
+            Err(FormatError::Malformed("parent recorded after its child"))
+        );
+
+        let tape = Tape::new().with_op_kinds();
+        // An input with a parent instead of its value as a constant operand.
+        let _ = tape.create_variable(1.0);
+        let _ = tape.record_nary(2.0, &[(0, 1.0)], OpKind::Input, &[]);
+        assert_eq!(
+            read(&saved(&tape)),
+            Err(FormatError::Malformed("operands do not fit operation kind"))
+        );
+    }
+
+    #[test]
+    fn test_write_rejects_external_records() {
+        let tape = Tape::new();
+        let x = tape.create_variable(1.0_f64);
+        let _ = tape.record_external(&[x], &[2.0], |adjoints: &[f64]| adjoints.to_vec());
+        assert_eq!(tape.write_to(Vec::new()), Err(FormatError::ExternalRecord));
+    }
+}
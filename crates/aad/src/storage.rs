@@ -0,0 +1,759 @@
+//! Storage backends for the nodes recorded on a [`Tape`](crate::tape::Tape).
+//!
+//! A node is the list of its parents: the indices of the nodes it was computed from,
+//! together with the local partial derivative with respect to each of them. Backends only
+//! need to append nodes, drop trailing nodes, and hand recorded nodes back in contiguous
+//! [`Segment`]s, so they are free to keep them in one buffer, in fixed-size chunks, or
+//! outside of memory altogether.
+
+mod file;
+
+pub use file::{FileStorage, SpillValue};
+use std::io;
+use std::ops::Range;
+
+/// An error returned by [`TapeStorage::push`] when a node cannot be recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageError {
+    /// The storage already holds its configured maximum number of nodes.
+    LimitReached(usize),
+    /// Memory for the node could not be allocated.
+    AllocationFailed,
+    /// The node could not be written to the backing file.
+    Io(io::ErrorKind),
+}
+
+/// A backend holding the nodes recorded on a tape.
+///
+/// Once [`TapeStorage::push`] has failed, it must keep failing until the storage is truncated
+/// to at most the length it had at that point, so that no node is ever recorded at the index
+/// handed out for a node that could not be.
+pub trait TapeStorage<F> {
+    /// Returns the number of recorded nodes.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if no nodes are recorded.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends a node with the given `(index, partial)` parents.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StorageError::LimitReached` if the storage is full
+    /// Returns `StorageError::AllocationFailed` if memory for the node could not be allocated
+    /// Returns `StorageError::Io` if the node could not be written to a backing file
+    fn push(&mut self, parents: &[(u32, F)]) -> Result<(), StorageError>;
+
+    /// Drops every node at or after `len`.
+    fn truncate(&mut self, len: usize);
+
+    /// Drops every node at or after `len` and gives the memory they used back, where possible.
+    ///
+    /// Called by consuming sweeps as they pass the nodes; the default only truncates.
+    fn release(&mut self, len: usize) {
+        self.truncate(len);
+    }
+
+    /// Calls `f` with segments covering the nodes in `nodes`, in increasing order.
+    fn visit(&self, nodes: Range<usize>, f: &mut dyn FnMut(Segment<'_, F>));
+
+    /// Calls `f` with segments covering the nodes in `nodes`, in decreasing order.
+    fn visit_rev(&self, nodes: Range<usize>, f: &mut dyn FnMut(Segment<'_, F>));
+}
+
+/// A contiguous run of recorded nodes.
+///
+/// The parents of the `k`-th node of the segment are `indices[ends[k - 1] - base..ends[k] - base]`
+/// with the matching partials, where `ends[-1]` is taken to be `base`.
+#[derive(Clone, Copy, Debug)]
+pub struct Segment<'a, F> {
+    first: usize,
+    base: u32,
+    ends: &'a [u32],
+    indices: &'a [u32],
+    partials: &'a [F],
+}
+
+impl<'a, F> Segment<'a, F> {
+    #[inline]
+    #[must_use]
+    /// Creates a segment of the nodes `first..first + ends.len()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - Index of the first node of the segment on the tape
+    /// * `base` - Offset of `indices[0]` in the numbering used by `ends`
+    /// * `ends` - End offset of the parents of each node
+    /// * `indices` - Parent indices of all nodes of the segment
+    /// * `partials` - Local partial derivatives matching `indices`
+    pub const fn new(
+        first: usize,
+        base: u32,
+        ends: &'a [u32],
+        indices: &'a [u32],
+        partials: &'a [F],
+    ) -> Self {
+        Self {
+            first,
+            base,
+            ends,
+            indices,
+            partials,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the index of the first node of the segment on the tape.
+    pub const fn first(&self) -> usize {
+        self.first
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the number of nodes in the segment.
+    pub const fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns `true` if the segment holds no nodes.
+    pub const fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the parent indices and partials of the `k`-th node of the segment.
+    pub fn parents(&self, k: usize) -> (&'a [u32], &'a [F]) {
+        let start = if k == 0 { self.base } else { self.ends[k - 1] };
+        let range = (start - self.base) as usize..(self.ends[k] - self.base) as usize;
+        (&self.indices[range.clone()], &self.partials[range])
+    }
+}
+
+/// Stores every node in a single growable buffer of each kind.
+///
+/// This is the default backend of a [`Tape`](crate::tape::Tape). The parents of node `i`
+/// are `indices[ends[i - 1]..ends[i]]` with the matching local partials in `partials`, so
+/// nodes take only as much space as they have parents.
+///
+/// A node takes a 4-byte end offset, plus a 4-byte index and a partial for each parent. With
+/// `f64` partials, an input takes 4 bytes, a unary node 16 bytes and a binary node 28 bytes,
+/// so tapes of mostly binary operations shrink far less than tapes of inputs and unary ones.
+#[derive(Debug, Default)]
+pub struct VecStorage<F> {
+    ends: Vec<u32>,
+    indices: Vec<u32>,
+    partials: Vec<F>,
+}
+
+impl<F> VecStorage<F> {
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            ends: Vec::new(),
+            indices: Vec::new(),
+            partials: Vec::new(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Creates a storage with room for `capacity` nodes of two parents each.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            ends: Vec::with_capacity(capacity),
+            indices: Vec::with_capacity(2 * capacity),
+            partials: Vec::with_capacity(2 * capacity),
+        }
+    }
+
+    /// Creates a storage with room for `nodes` nodes with `parents` parents in total.
+    fn try_with_capacity(nodes: usize, parents: usize) -> Result<Self, StorageError> {
+        let mut storage = Self::new();
+        storage
+            .ends
+            .try_reserve_exact(nodes)
+            .and_then(|()| storage.indices.try_reserve_exact(parents))
+            .and_then(|()| storage.partials.try_reserve_exact(parents))
+            .map_err(|_| StorageError::AllocationFailed)?;
+        Ok(storage)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the number of nodes the storage can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.ends.capacity()
+    }
+
+    /// Returns `true` if a node with `parents` parents fits without reallocating.
+    fn has_room_for(&self, parents: usize) -> bool {
+        self.ends.len() < self.ends.capacity()
+            && self.indices.len() + parents <= self.indices.capacity()
+    }
+
+    fn truncate_nodes(&mut self, len: usize) {
+        if len >= self.ends.len() {
+            return;
+        }
+        let end = self.start(len) as usize;
+        self.ends.truncate(len);
+        self.indices.truncate(end);
+        self.partials.truncate(end);
+    }
+
+    /// Truncates to `len` nodes and shrinks the buffers once at most half of them is used.
+    fn release_nodes(&mut self, len: usize) {
+        self.truncate_nodes(len);
+        if self.ends.len() <= self.ends.capacity() / 2 {
+            self.ends.shrink_to_fit();
+            self.indices.shrink_to_fit();
+            self.partials.shrink_to_fit();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.ends.clear();
+        self.indices.clear();
+        self.partials.clear();
+    }
+
+    #[inline]
+    fn start(&self, node: usize) -> u32 {
+        node.checked_sub(1)
+            .map_or(0, |previous| self.ends[previous])
+    }
+
+    /// Returns the nodes `local` of this buffer as a segment starting at tape index `first`.
+    #[inline]
+    fn segment(&self, first: usize, local: Range<usize>) -> Segment<'_, F> {
+        let base = self.start(local.start);
+        let end = self.start(local.end);
+        let parents = base as usize..end as usize;
+        Segment::new(
+            first,
+            base,
+            &self.ends[local],
+            &self.indices[parents.clone()],
+            &self.partials[parents],
+        )
+    }
+}
+
+impl<F: Copy> TapeStorage<F> for VecStorage<F> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    #[inline]
+    fn push(&mut self, parents: &[(u32, F)]) -> Result<(), StorageError> {
+        self.indices.extend(parents.iter().map(|&(idx, _)| idx));
+        self.partials
+            .extend(parents.iter().map(|&(_, partial)| partial));
+        self.ends.push(to_u32(self.indices.len()));
+        Ok(())
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.truncate_nodes(len);
+    }
+
+    #[inline]
+    fn release(&mut self, len: usize) {
+        self.release_nodes(len);
+    }
+
+    #[inline]
+    fn visit(&self, nodes: Range<usize>, f: &mut dyn FnMut(Segment<'_, F>)) {
+        if !nodes.is_empty() {
+            f(self.segment(nodes.start, nodes));
+        }
+    }
+
+    #[inline]
+    fn visit_rev(&self, nodes: Range<usize>, f: &mut dyn FnMut(Segment<'_, F>)) {
+        self.visit(nodes, f);
+    }
+}
+
+/// Stores nodes in fixed-size chunks that are never reallocated.
+///
+/// Recording never moves nodes that are already stored, so growing a very large tape costs
+/// one allocation per chunk instead of repeatedly copying the whole tape. Chunks dropped by
+/// rewinding the tape are kept for reuse. An optional node limit, and any failure to
+/// allocate a chunk, make recording fail with a recoverable error: variables that could not
+/// be recorded keep their values, but sweeps seeded with them return
+/// `GradientError::StorageFull`, and [`Tape::storage_error`](crate::tape::Tape::storage_error)
+/// reports the failure.
+///
+/// # Examples
+///
+/// ```
+/// use aad::Tape;
+/// use aad::gradients::GradientError;
+/// use aad::storage::ChunkedStorage;
+///
+/// let tape = Tape::with_storage(ChunkedStorage::new(1024).with_node_limit(3));
+/// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+/// let z = x * y;
+/// assert_eq!(z.compute_gradients().unwrap().get_gradient(&x), Ok(3.0));
+///
+/// let w = z * x;
+/// assert_eq!(w.value(), 12.0);
+/// assert_eq!(w.compute_gradients().err(), Some(GradientError::StorageFull(3)));
+/// ```
+#[derive(Debug)]
+pub struct ChunkedStorage<F> {
+    chunks: Vec<VecStorage<F>>,
+    /// Index of the first node of each chunk.
+    firsts: Vec<usize>,
+    spare: Vec<VecStorage<F>>,
+    chunk_len: usize,
+    node_limit: Option<usize>,
+    len: usize,
+    /// Length at which allocating a chunk failed, if it has not been truncated below since.
+    failed_at: Option<usize>,
+}
+
+impl<F> ChunkedStorage<F> {
+    #[inline]
+    #[must_use]
+    /// Creates an empty storage that allocates `chunk_len` nodes at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_len` is zero.
+    pub const fn new(chunk_len: usize) -> Self {
+        assert!(chunk_len > 0, "chunks must hold at least one node");
+        Self {
+            chunks: Vec::new(),
+            firsts: Vec::new(),
+            spare: Vec::new(),
+            chunk_len,
+            node_limit: None,
+            len: 0,
+            failed_at: None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Limits the storage to at most `node_limit` nodes.
+    pub const fn with_node_limit(mut self, node_limit: usize) -> Self {
+        self.node_limit = Some(node_limit);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the maximum number of nodes, if limited.
+    pub const fn node_limit(&self) -> Option<usize> {
+        self.node_limit
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the number of allocated chunks, including the ones kept for reuse.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len() + self.spare.len()
+    }
+
+    /// Makes sure the last chunk has room for a node with `parents` parents.
+    fn reserve(&mut self, parents: usize) -> Result<(), StorageError> {
+        if self
+            .chunks
+            .last()
+            .is_some_and(|chunk| chunk.has_room_for(parents))
+        {
+            return Ok(());
+        }
+        let parents_capacity = parents.max(2 * self.chunk_len);
+        let chunk = match self
+            .spare
+            .iter()
+            .rposition(|chunk| chunk.indices.capacity() >= parents_capacity)
+        {
+            Some(position) => self.spare.swap_remove(position),
+            None => VecStorage::try_with_capacity(self.chunk_len, parents_capacity)?,
+        };
+        self.chunks.push(chunk);
+        self.firsts.push(self.len);
+        Ok(())
+    }
+
+    /// Returns the chunks overlapping `nodes` with the local range of nodes in each.
+    fn overlapping(
+        &self,
+        nodes: Range<usize>,
+    ) -> impl DoubleEndedIterator<Item = (usize, &VecStorage<F>, Range<usize>)> {
+        let first = self.firsts.partition_point(|&first| first <= nodes.start);
+        let last = self.firsts.partition_point(|&first| first < nodes.end);
+        let chunks = if nodes.is_empty() {
+            0..0
+        } else {
+            first.saturating_sub(1)..last
+        };
+        chunks.map(move |c| {
+            let first = self.firsts[c];
+            let local = nodes.start.max(first) - first
+                ..nodes.end.min(first + self.chunks[c].ends.len()) - first;
+            (first, &self.chunks[c], local)
+        })
+    }
+}
+
+impl<F: Copy> TapeStorage<F> for ChunkedStorage<F> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn push(&mut self, parents: &[(u32, F)]) -> Result<(), StorageError> {
+        if let Some(limit) = self.node_limit.filter(|&limit| self.len >= limit) {
+            return Err(StorageError::LimitReached(limit));
+        }
+        if self.failed_at.is_some() {
+            return Err(StorageError::AllocationFailed);
+        }
+        if let Err(error) = self.reserve(parents.len()) {
+            self.failed_at = Some(self.len);
+            return Err(error);
+        }
+        let chunk = self
+            .chunks
+            .last_mut()
+            .expect("a chunk has just been reserved");
+        chunk.push(parents)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        if self.failed_at.is_some_and(|failed_at| len <= failed_at) {
+            self.failed_at = None;
+        }
+        if len >= self.len {
+            return;
+        }
+        let kept = self.firsts.partition_point(|&first| first < len);
+        for mut chunk in self.chunks.drain(kept..) {
+            chunk.clear();
+            self.spare.push(chunk);
+        }
+        self.firsts.truncate(kept);
+        if let (Some(chunk), Some(&first)) = (self.chunks.last_mut(), self.firsts.last()) {
+            chunk.truncate_nodes(len - first);
+        }
+        self.len = len;
+    }
+
+    #[inline]
+    fn release(&mut self, len: usize) {
+        self.truncate(len);
+        self.spare = Vec::new();
+    }
+
+    #[inline]
+    fn visit(&self, nodes: Range<usize>, f: &mut dyn FnMut(Segment<'_, F>)) {
+        for (first, chunk, local) in self.overlapping(nodes) {
+            f(chunk.segment(first + local.start, local));
+        }
+    }
+
+    #[inline]
+    fn visit_rev(&self, nodes: Range<usize>, f: &mut dyn FnMut(Segment<'_, F>)) {
+        for (first, chunk, local) in self.overlapping(nodes).rev() {
+            f(chunk.segment(first + local.start, local));
+        }
+    }
+}
+
+/// The storage of a tape: the built-in buffer, or a user-supplied backend.
+pub(crate) enum Storage<F> {
+    Vec(VecStorage<F>),
+    Custom(Box<dyn TapeStorage<F> + Send>),
+}
+
+impl<F> Default for Storage<F> {
+    #[inline]
+    fn default() -> Self {
+        Self::Vec(VecStorage::new())
+    }
+}
+
+impl<F> std::fmt::Debug for Storage<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Vec(storage) => f.debug_tuple("Vec").field(&storage.ends.len()).finish(),
+            Self::Custom(storage) => f.debug_tuple("Custom").field(&storage.len()).finish(),
+        }
+    }
+}
+
+impl<F> Storage<F> {
+    #[cfg(test)]
+    pub fn capacity(&self) -> usize {
+        match self {
+            Self::Vec(storage) => storage.capacity(),
+            Self::Custom(_) => 0,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Vec(storage) => storage.ends.len(),
+            Self::Custom(storage) => storage.len(),
+        }
+    }
+
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        match self {
+            Self::Vec(storage) => storage.truncate_nodes(len),
+            Self::Custom(storage) => storage.truncate(len),
+        }
+    }
+
+    #[inline]
+    pub fn release(&mut self, len: usize) {
+        match self {
+            Self::Vec(storage) => storage.release_nodes(len),
+            Self::Custom(storage) => storage.release(len),
+        }
+    }
+}
+
+impl<F: Copy> Storage<F> {
+    #[inline]
+    pub fn push(&mut self, parents: &[(u32, F)]) -> Result<(), StorageError> {
+        match self {
+            Self::Vec(storage) => storage.push(parents),
+            Self::Custom(storage) => storage.push(parents),
+        }
+    }
+
+    #[inline]
+    /// Calls `f` with the index and parents of every node in `nodes`, in increasing order.
+    pub fn for_each(&self, nodes: Range<usize>, mut f: impl FnMut(usize, &[u32], &[F])) {
+        let mut visit = |segment: Segment<'_, F>| {
+            for k in 0..segment.len() {
+                let (indices, partials) = segment.parents(k);
+                f(segment.first() + k, indices, partials);
+            }
+        };
+        match self {
+            Self::Vec(storage) => storage.visit(nodes, &mut visit),
+            Self::Custom(storage) => storage.visit(nodes, &mut visit),
+        }
+    }
+
+    #[inline]
+    /// Calls `f` with the index and parents of every node in `nodes`, in decreasing order.
+    pub fn for_each_rev(&self, nodes: Range<usize>, mut f: impl FnMut(usize, &[u32], &[F])) {
+        let mut visit = |segment: Segment<'_, F>| {
+            for k in (0..segment.len()).rev() {
+                let (indices, partials) = segment.parents(k);
+                f(segment.first() + k, indices, partials);
+            }
+        };
+        match self {
+            Self::Vec(storage) => storage.visit_rev(nodes, &mut visit),
+            Self::Custom(storage) => storage.visit_rev(nodes, &mut visit),
+        }
+    }
+}
+
+#[inline]
+/// Converts a node or parent offset to the `u32` used by the storages.
+pub(crate) fn to_u32(index: usize) -> u32 {
+    u32::try_from(index).expect("tape cannot hold more than `u32::MAX` nodes or parents")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradients::GradientError;
+    use crate::tape::Tape;
+    use crate::variable::Variable;
+
+    fn collect<F: Copy>(
+        storage: &dyn TapeStorage<F>,
+        nodes: Range<usize>,
+    ) -> Vec<(Vec<u32>, Vec<F>)> {
+        let mut parents = Vec::new();
+        storage.visit(nodes, &mut |segment| {
+            for k in 0..segment.len() {
+                let (indices, partials) = segment.parents(k);
+                parents.push((indices.to_vec(), partials.to_vec()));
+            }
+        });
+        parents
+    }
+
+    #[test]
+    fn test_vec_storage_layout() {
+        let mut storage = VecStorage::new();
+        storage.push(&[]).unwrap();
+        storage.push(&[(0, 2.0)]).unwrap();
+        storage.push(&[(0, 3.0), (1, 4.0)]).unwrap();
+        storage.push(&[(0, 5.0), (1, 6.0), (2, 7.0)]).unwrap();
+        assert_eq!(storage.len(), 4);
+        assert_eq!(storage.indices, [0, 0, 1, 0, 1, 2]);
+        assert_eq!(
+            collect(&storage, 2..4)[1],
+            (vec![0, 1, 2], vec![5.0, 6.0, 7.0])
+        );
+
+        storage.truncate(2);
+        assert_eq!(storage.len(), 2);
+        assert_eq!(storage.indices, [0]);
+        assert_eq!(storage.partials, [2.0]);
+    }
+
+    #[test]
+    fn test_chunked_storage_matches_vec_storage() {
+        let mut chunked = ChunkedStorage::new(2);
+        let mut vec = VecStorage::new();
+        let nodes: [&[(u32, f64)]; 6] = [
+            &[],
+            &[(0, 1.0)],
+            &[(0, 2.0), (1, 3.0)],
+            &[(0, 1.0), (1, 2.0), (2, 3.0), (1, 4.0), (0, 5.0)],
+            &[],
+            &[(4, 6.0)],
+        ];
+        for parents in nodes {
+            chunked.push(parents).unwrap();
+            vec.push(parents).unwrap();
+        }
+        assert_eq!(chunked.len(), 6);
+        for range in [0..6, 1..5, 3..4, 2..2] {
+            assert_eq!(collect(&chunked, range.clone()), collect(&vec, range));
+        }
+        let mut reversed = Vec::new();
+        chunked.visit_rev(0..6, &mut |segment| reversed.push(segment.first()));
+        assert_eq!(reversed, [5, 3, 2, 0]);
+    }
+
+    #[test]
+    fn test_chunked_storage_never_moves_nodes() {
+        let mut storage = ChunkedStorage::new(4);
+        storage.push(&[(0, 1.0)]).unwrap();
+        let first = storage.chunks[0].partials.as_ptr();
+        for i in 0..100 {
+            storage.push(&[(i, 1.0), (i, 2.0)]).unwrap();
+        }
+        assert_eq!(storage.chunks[0].partials.as_ptr(), first);
+        assert_eq!(storage.chunk_count(), 26);
+
+        storage.truncate(3);
+        assert_eq!(storage.len(), 3);
+        assert_eq!(storage.chunk_count(), 26);
+        for i in 0..100 {
+            storage.push(&[(i, 1.0)]).unwrap();
+        }
+        assert_eq!(storage.chunk_count(), 26);
+        assert_eq!(storage.chunks[0].partials.as_ptr(), first);
+    }
+
+    #[test]
+    fn test_release_frees_memory() {
+        let mut storage = ChunkedStorage::new(4);
+        for i in 0..40 {
+            storage.push(&[(i, 1.0)]).unwrap();
+        }
+        assert_eq!(storage.chunk_count(), 10);
+        storage.truncate(30);
+        storage.release(10);
+        assert_eq!(storage.len(), 10);
+        assert_eq!(storage.chunk_count(), 3);
+
+        let mut storage = VecStorage::with_capacity(40);
+        for i in 0..40 {
+            storage.push(&[(i, 1.0)]).unwrap();
+        }
+        storage.release(30);
+        assert_eq!(storage.capacity(), 40);
+        storage.release(10);
+        assert_eq!(storage.capacity(), 10);
+        assert_eq!(storage.indices, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_chunked_tape_gradients() {
+        fn record(tape: &Tape<f64>) -> (Vec<Variable<'_, f64>>, Vec<Variable<'_, f64>>) {
+            let x = tape
+                .create_variables_iter(&[0.5, 2.0, 3.0])
+                .collect::<Vec<_>>();
+            let y = [
+                x.iter().map(|xi| xi.sin() * x[0]).sum::<Variable<f64>>(),
+                Variable::dot(&x, &x) / x[1],
+                x.iter().product::<Variable<f64>>().ln(),
+            ];
+            (x, y.to_vec())
+        }
+        let vec = Tape::new();
+        let chunked = Tape::with_storage(ChunkedStorage::new(3));
+        let (x, y) = record(&vec);
+        let (cx, cy) = record(&chunked);
+        assert_eq!(
+            vec.jacobian(&y, &x).unwrap(),
+            chunked.jacobian(&cy, &cx).unwrap()
+        );
+        let seeds = x.iter().map(|x| (*x, 1.0)).collect::<Vec<_>>();
+        let chunked_seeds = cx.iter().map(|x| (*x, 1.0)).collect::<Vec<_>>();
+        assert_eq!(
+            vec.jvp(&seeds, &y).unwrap(),
+            chunked.jvp(&chunked_seeds, &cy).unwrap()
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp, clippy::many_single_char_names)]
+    fn test_node_limit_is_recoverable() {
+        let tape = Tape::with_storage(ChunkedStorage::new(2).with_node_limit(4));
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let mark = tape.mark();
+        let z = (x * y).sin();
+        let w = z * x;
+        let v = w + 1.0;
+        assert_eq!(tape.len(), 4);
+        assert_eq!(v.value(), (6.0_f64).sin() * 2.0 + 1.0);
+        assert_eq!(
+            v.compute_gradients().err(),
+            Some(GradientError::StorageFull(4))
+        );
+        assert_eq!(
+            tape.backward(&[(z, 1.0), (w, 1.0)]).err(),
+            Some(GradientError::StorageFull(4))
+        );
+        assert!(z.compute_gradients().is_ok());
+        assert_eq!(tape.storage_error(), Some(StorageError::LimitReached(4)));
+
+        tape.rewind_to(mark);
+        assert_eq!(tape.storage_error(), None);
+        let z = x * y;
+        assert_eq!(
+            z.compute_gradients().unwrap().get_gradients(&[x, y]),
+            Ok([3.0, 2.0])
+        );
+    }
+
+    #[test]
+    fn test_tapes_with_custom_storage_are_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        assert_send(&Tape::<f64>::with_storage(ChunkedStorage::new(2)));
+        assert_send(&Tape::<f64>::new().with_op_kinds());
+    }
+}
@@ -0,0 +1,426 @@
+use super::{Segment, StorageError, TapeStorage, VecStorage};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A value that can be written to and read back from a [`FileStorage`].
+pub trait SpillValue: Copy {
+    /// Number of bytes of the encoded value.
+    const SIZE: usize;
+
+    /// Appends the encoded value to `bytes`.
+    fn write_bytes(self, bytes: &mut Vec<u8>);
+
+    /// Decodes a value from exactly [`SpillValue::SIZE`] bytes.
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_spill_value {
+    ($($ty:ty),*) => {
+        $(
+            impl SpillValue for $ty {
+                const SIZE: usize = size_of::<$ty>();
+
+                #[inline]
+                fn write_bytes(self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_le_bytes());
+                }
+
+                #[inline]
+                fn read_bytes(bytes: &[u8]) -> Self {
+                    Self::from_le_bytes(bytes.try_into().expect("value has a fixed size"))
+                }
+            }
+        )*
+    };
+}
+
+impl_spill_value!(f32, f64, u32);
+
+/// A block of nodes written to the file.
+#[derive(Clone, Copy, Debug)]
+struct Block {
+    first: usize,
+    offset: u64,
+    nodes: usize,
+    parents: usize,
+}
+
+/// Stores nodes in a temporary file, keeping only the most recent block in memory.
+///
+/// Nodes are buffered in memory and appended to the file one block at a time. Sweeps read
+/// the blocks back one at a time, last block first for reverse sweeps, so a tape can grow
+/// well beyond the available memory. Only the position of each block is kept in memory.
+/// The file is deleted when the storage is dropped.
+///
+/// A failure to write a block makes recording fail with a recoverable error, like a full
+/// [`ChunkedStorage`](super::ChunkedStorage). Failures to read a block back are not
+/// recoverable and panic.
+///
+/// # Examples
+///
+/// ```
+/// use aad::Tape;
+/// use aad::storage::FileStorage;
+///
+/// let tape = Tape::with_storage(FileStorage::new().unwrap().with_block_len(2));
+/// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+/// let z = (x * y).sin() + x;
+/// let grads = z.compute_gradients().unwrap();
+/// assert_eq!(grads.get_gradient(&y), Ok(2.0 * 6.0_f64.cos()));
+/// ```
+#[derive(Debug)]
+pub struct FileStorage<F> {
+    file: File,
+    path: PathBuf,
+    blocks: Vec<Block>,
+    tail: VecStorage<F>,
+    block_len: usize,
+    /// Length of the valid contents of the file.
+    file_len: u64,
+    /// Reused buffer for encoding blocks.
+    bytes: Vec<u8>,
+    /// Length at which writing a block failed and the reason, if it has not been truncated
+    /// below since.
+    failed: Option<(usize, io::ErrorKind)>,
+}
+
+impl<F> FileStorage<F> {
+    /// Default number of nodes per block.
+    pub const DEFAULT_BLOCK_LEN: usize = 1 << 16;
+
+    #[inline]
+    /// Creates a storage backed by a new file in the system temporary directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file cannot be created
+    pub fn new() -> io::Result<Self> {
+        Self::in_dir(std::env::temp_dir())
+    }
+
+    #[inline]
+    /// Creates a storage backed by a new file in `dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file cannot be created
+    pub fn in_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "aad-tape-{}-{}.bin",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = dir.as_ref().join(name);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self {
+            file,
+            path,
+            blocks: Vec::new(),
+            tail: VecStorage::new(),
+            block_len: Self::DEFAULT_BLOCK_LEN,
+            file_len: 0,
+            bytes: Vec::new(),
+            failed: None,
+        })
+    }
+
+    #[inline]
+    #[must_use]
+    /// Sets the number of nodes buffered in memory before they are written to the file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_len` is zero.
+    pub fn with_block_len(mut self, block_len: usize) -> Self {
+        assert!(block_len > 0, "blocks must hold at least one node");
+        self.block_len = block_len;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the number of nodes written to the file so far.
+    pub fn spilled_len(&self) -> usize {
+        self.blocks
+            .last()
+            .map_or(0, |block| block.first + block.nodes)
+    }
+}
+
+impl<F: SpillValue> FileStorage<F> {
+    /// Writes the in-memory nodes to the end of the file as a new block.
+    fn flush(&mut self) -> io::Result<()> {
+        self.bytes.clear();
+        for &value in self.tail.ends.iter().chain(&self.tail.indices) {
+            value.write_bytes(&mut self.bytes);
+        }
+        for &value in &self.tail.partials {
+            value.write_bytes(&mut self.bytes);
+        }
+        self.file.seek(SeekFrom::Start(self.file_len))?;
+        self.file.write_all(&self.bytes)?;
+        self.blocks.push(Block {
+            first: self.spilled_len(),
+            offset: self.file_len,
+            nodes: self.tail.ends.len(),
+            parents: self.tail.indices.len(),
+        });
+        self.file_len += self.bytes.len() as u64;
+        self.tail.clear();
+        Ok(())
+    }
+
+    /// Reads `block` from the file into `buffer`.
+    fn read(&self, block: &Block, buffer: &mut VecStorage<F>) {
+        let len = 4 * (block.nodes + block.parents) + F::SIZE * block.parents;
+        let mut bytes = vec![0; len];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(block.offset))
+            .and_then(|_| file.read_exact(&mut bytes))
+            .unwrap_or_else(|error| panic!("failed to read spilled tape block: {error}"));
+
+        let (ends, rest) = bytes.split_at(4 * block.nodes);
+        let (indices, partials) = rest.split_at(4 * block.parents);
+        buffer.clear();
+        buffer
+            .ends
+            .extend(ends.chunks_exact(4).map(u32::read_bytes));
+        buffer
+            .indices
+            .extend(indices.chunks_exact(4).map(u32::read_bytes));
+        buffer
+            .partials
+            .extend(partials.chunks_exact(F::SIZE).map(F::read_bytes));
+    }
+
+    /// Returns the blocks overlapping `nodes` with the local range of nodes in each.
+    fn overlapping(
+        &self,
+        nodes: Range<usize>,
+    ) -> impl DoubleEndedIterator<Item = (Block, Range<usize>)> {
+        let first = self
+            .blocks
+            .partition_point(|block| block.first <= nodes.start);
+        let last = self.blocks.partition_point(|block| block.first < nodes.end);
+        let blocks = if nodes.is_empty() {
+            &[][..]
+        } else {
+            &self.blocks[first.saturating_sub(1)..last]
+        };
+        blocks
+            .iter()
+            .map(move |&block| {
+                let local = nodes.start.max(block.first) - block.first
+                    ..nodes.end.min(block.first + block.nodes) - block.first;
+                (block, local)
+            })
+            .filter(|(_, local)| !local.is_empty())
+    }
+
+    /// Returns the nodes of `nodes` that are still held in memory, relative to the tail.
+    fn tail_range(&self, nodes: &Range<usize>) -> Range<usize> {
+        let first = self.spilled_len();
+        let start = nodes.start.max(first) - first;
+        let end = nodes.end.max(first) - first;
+        start.min(end)..end
+    }
+}
+
+impl<F> Drop for FileStorage<F> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl<F: SpillValue> TapeStorage<F> for FileStorage<F> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.spilled_len() + self.tail.ends.len()
+    }
+
+    #[inline]
+    fn push(&mut self, parents: &[(u32, F)]) -> Result<(), StorageError> {
+        if let Some((_, kind)) = self.failed {
+            return Err(StorageError::Io(kind));
+        }
+        if self.tail.ends.len() >= self.block_len
+            && let Err(error) = self.flush()
+        {
+            self.failed = Some((self.len(), error.kind()));
+            return Err(StorageError::Io(error.kind()));
+        }
+        self.tail.push(parents)
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        if self.failed.is_some_and(|(failed_at, _)| len <= failed_at) {
+            self.failed = None;
+        }
+        let first = self.spilled_len();
+        if len >= first {
+            self.tail.truncate_nodes(len - first);
+            return;
+        }
+
+        let kept = self.blocks.partition_point(|block| block.first < len);
+        self.blocks.truncate(kept);
+        self.tail.clear();
+        self.file_len = self.blocks.last().map_or(0, |block| block.offset);
+        if let Some(block) = self.blocks.pop() {
+            // Reload the block holding the new end of the tape so it can keep growing.
+            let mut tail = VecStorage::new();
+            self.read(&block, &mut tail);
+            tail.truncate_nodes(len - block.first);
+            self.tail = tail;
+        }
+        let _ = self.file.set_len(self.file_len);
+    }
+
+    #[inline]
+    fn visit(&self, nodes: Range<usize>, f: &mut dyn FnMut(Segment<'_, F>)) {
+        let mut buffer = VecStorage::new();
+        for (block, local) in self.overlapping(nodes.clone()) {
+            self.read(&block, &mut buffer);
+            f(buffer.segment(block.first + local.start, local));
+        }
+        let tail = self.tail_range(&nodes);
+        if !tail.is_empty() {
+            f(self.tail.segment(self.spilled_len() + tail.start, tail));
+        }
+    }
+
+    #[inline]
+    fn visit_rev(&self, nodes: Range<usize>, f: &mut dyn FnMut(Segment<'_, F>)) {
+        let tail = self.tail_range(&nodes);
+        if !tail.is_empty() {
+            f(self.tail.segment(self.spilled_len() + tail.start, tail));
+        }
+        let mut buffer = VecStorage::new();
+        for (block, local) in self.overlapping(nodes).rev() {
+            self.read(&block, &mut buffer);
+            f(buffer.segment(block.first + local.start, local));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradients::GradientError;
+    use crate::tape::Tape;
+    use crate::variable::Variable;
+
+    fn record(tape: &Tape<f64>) -> (Vec<Variable<'_, f64>>, Vec<Variable<'_, f64>>) {
+        let x = tape
+            .create_variables_iter(&[0.5, 2.0, 3.0])
+            .collect::<Vec<_>>();
+        let mut y = x[0];
+        for i in 0..20 {
+            y = (y * x[1]).sin() + x[2] / (y + f64::from(i));
+        }
+        let outputs = vec![
+            y,
+            Variable::dot(&x, &x),
+            x.iter().product::<Variable<f64>>(),
+        ];
+        (x, outputs)
+    }
+
+    #[test]
+    fn test_file_storage_matches_vec_storage() {
+        let memory = Tape::new();
+        let file = Tape::with_storage(FileStorage::new().unwrap().with_block_len(7));
+        let (x, y) = record(&memory);
+        let (fx, fy) = record(&file);
+        assert_eq!(memory.len(), file.len());
+
+        assert_eq!(
+            memory.jacobian(&y, &x).unwrap(),
+            file.jacobian(&fy, &fx).unwrap()
+        );
+        let seeds = [(x[0], 1.0), (x[2], -0.5)];
+        let file_seeds = [(fx[0], 1.0), (fx[2], -0.5)];
+        assert_eq!(
+            memory.jvp(&seeds, &y).unwrap(),
+            file.jvp(&file_seeds, &fy).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_file_storage_visits_spilled_and_buffered_nodes() {
+        let mut storage = FileStorage::new().unwrap().with_block_len(2);
+        for i in 0..5 {
+            storage.push(&[(i, f64::from(i))]).unwrap();
+        }
+        assert_eq!(storage.spilled_len(), 4);
+        for nodes in [0..5, 1..3, 3..5, 4..5, 2..2] {
+            let mut firsts = Vec::new();
+            let mut partials = Vec::new();
+            storage.visit_rev(nodes.clone(), &mut |segment| {
+                firsts.push(segment.first());
+                for k in (0..segment.len()).rev() {
+                    partials.extend_from_slice(segment.parents(k).1);
+                }
+            });
+            let expected = nodes
+                .clone()
+                .rev()
+                .map(|i| f64::from(u32::try_from(i).unwrap()))
+                .collect::<Vec<_>>();
+            assert_eq!(partials, expected, "{nodes:?}");
+            assert!(firsts.windows(2).all(|w| w[0] > w[1]));
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_file_storage_rewind_across_blocks() {
+        let storage = FileStorage::new().unwrap().with_block_len(3);
+        let path = storage.path().to_path_buf();
+        let tape = Tape::with_storage(storage);
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let z = x * y;
+        let mark = tape.mark();
+        for _ in 0..3 {
+            let mut w = z;
+            for _ in 0..10 {
+                w = w * x + y;
+            }
+            let grads = w.compute_gradients().unwrap();
+            assert_eq!(grads.get_gradient(&y).unwrap(), 3071.0);
+            assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            tape.rewind_to(mark);
+            assert_eq!(tape.len(), 3);
+        }
+        let grads = z.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradients(&[x, y]).unwrap(), [3.0, 2.0]);
+        assert_eq!(
+            (z * x).compute_gradients().unwrap().get_gradient(&x),
+            Ok(12.0)
+        );
+
+        tape.clear();
+        assert_eq!(
+            z.compute_gradients().err(),
+            Some(GradientError::StaleVariable(2))
+        );
+        drop(tape);
+        assert!(!path.exists());
+    }
+}
@@ -1,20 +1,110 @@
+use crate::external::ExternalRecord;
+use crate::gradients::{GradientBuffer, GradientError, Gradients};
 use crate::operation_record::OperationRecord;
+use crate::replay::{Branch, BranchTest, OpKind, OpLog, Operand};
+use crate::storage::{Storage, StorageError, TapeStorage, VecStorage, to_u32};
 use crate::variable::Variable;
-use num_traits::Zero;
-use std::cell::RefCell;
+use num_traits::{One, Zero};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BinaryHeap};
 use std::fmt::Debug;
+use std::marker::PhantomData;
+
+/// Number of nodes a consuming sweep processes between releasing memory.
+const DRAIN_STEP: usize = 1 << 16;
+
+/// Number of adjoints in each block allocated by a consuming sweep.
+const ADJOINT_BLOCK: usize = 1 << 12;
 
 #[derive(Debug, Default)]
 pub struct Tape<F: Sized> {
-    pub(crate) operations: RefCell<Vec<OperationRecord<F>>>,
+    pub(crate) operations: RefCell<Storage<F>>,
+    generation: Cell<usize>,
+    /// Stack of `(generation, length)` pairs recording each rewind, kept with strictly
+    /// increasing lengths so that the oldest applicable entry bounds every later one.
+    rewinds: RefCell<Vec<(usize, usize)>>,
+    /// Black-box routines recorded with [`Tape::record_external`], ordered by first output.
+    pub(crate) externals: RefCell<Vec<ExternalRecord<F>>>,
+    /// Operation kinds and comparisons, recorded on tapes created with [`Tape::with_op_kinds`].
+    pub(crate) ops: RefCell<Option<OpLog<F>>>,
+    /// Whether `ops` is set, read by comparisons without borrowing it.
+    records_ops: Cell<bool>,
+    /// First error of the storage, with the index of the node that could not be recorded.
+    storage_error: Cell<Option<(usize, StorageError)>>,
+    /// Generation of a stale operand of the node being recorded, which the node inherits so
+    /// that results computed from invalidated variables are reported stale as well.
+    stale: Cell<Option<usize>>,
+}
+
+/// A position on a [`Tape`] that the tape can later be rewound to.
+///
+/// Obtained from [`Tape::mark`] and consumed by [`Tape::rewind_to`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TapeMark {
+    len: usize,
+    generation: usize,
+    branches: usize,
+}
+
+/// A [`Tape`] handle whose variables are branded with the invariant lifetime `'id`.
+///
+/// Created by [`Tape::scope`]. Every scope gets a distinct `'id`, so combining variables
+/// recorded in two different scopes is rejected at compile time instead of at runtime:
+///
+/// ```compile_fail
+/// use aad::Tape;
+///
+/// Tape::scope(|outer| {
+///     Tape::scope(|inner| {
+///         let x = outer.create_variable(1.0_f64);
+///         let y = inner.create_variable(2.0_f64);
+///         (x + y).value()
+///     })
+/// });
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct BrandedTape<'id, F> {
+    tape: &'id Tape<F>,
+    brand: PhantomData<fn(&'id ()) -> &'id ()>,
 }
 
 impl<F> Tape<F> {
+    #[inline]
+    /// Runs `f` with a fresh [`BrandedTape`] whose variables cannot escape the closure
+    /// or be combined with variables of any other tape.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let dx = Tape::scope(|tape| {
+    ///     let x = tape.create_variable(3.0_f64);
+    ///     let y = x * x;
+    ///     y.compute_gradients().unwrap().get_gradient(&x).unwrap()
+    /// });
+    /// assert_eq!(dx, 6.0);
+    /// ```
+    pub fn scope<R>(f: impl for<'id> FnOnce(BrandedTape<'id, F>) -> R) -> R {
+        let tape = Self::new();
+        f(BrandedTape {
+            tape: &tape,
+            brand: PhantomData,
+        })
+    }
+
     #[inline]
     #[must_use]
     pub const fn new() -> Self {
         Self {
-            operations: RefCell::new(Vec::new()),
+            operations: RefCell::new(Storage::Vec(VecStorage::new())),
+            generation: Cell::new(0),
+            rewinds: RefCell::new(Vec::new()),
+            externals: RefCell::new(Vec::new()),
+            ops: RefCell::new(None),
+            records_ops: Cell::new(false),
+            storage_error: Cell::new(None),
+            stale: Cell::new(None),
         }
     }
 
@@ -22,42 +112,1472 @@ impl<F> Tape<F> {
     #[must_use]
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
-            operations: RefCell::new(Vec::with_capacity(capacity)),
+            operations: RefCell::new(Storage::Vec(VecStorage::with_capacity(capacity))),
+            generation: Cell::new(0),
+            rewinds: RefCell::new(Vec::new()),
+            externals: RefCell::new(Vec::new()),
+            ops: RefCell::new(None),
+            records_ops: Cell::new(false),
+            storage_error: Cell::new(None),
+            stale: Cell::new(None),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the number of nodes currently recorded on the tape.
+    pub fn len(&self) -> usize {
+        self.operations.borrow().len()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns `true` if no nodes are recorded on the tape.
+    pub fn is_empty(&self) -> bool {
+        self.operations.borrow().len() == 0
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns a mark for the current end of the tape.
+    ///
+    /// Variables created before the mark stay valid after [`Tape::rewind_to`] is called
+    /// with it, while variables created after it become stale.
+    pub fn mark(&self) -> TapeMark {
+        TapeMark {
+            len: self.len(),
+            generation: self.generation.get(),
+            branches: self.ops.borrow().as_ref().map_or(0, OpLog::branch_count),
+        }
+    }
+
+    #[inline]
+    /// Drops every node recorded after `mark`, keeping the allocation for reuse.
+    ///
+    /// Variables created after `mark` are invalidated: computing or reading their
+    /// gradients returns `GradientError::StaleVariable`, and so does computing the gradients
+    /// of any result of an operation on them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mark` itself was taken after a point the tape has since been rewound past.
+    pub fn rewind_to(&self, mark: TapeMark) {
+        assert!(
+            self.is_live_len(mark.len, mark.generation),
+            "tape mark has been invalidated by an earlier rewind"
+        );
+        self.truncate(mark.len, mark.branches);
+    }
+
+    #[inline]
+    /// Drops every recorded node, keeping the allocation for reuse.
+    ///
+    /// All variables created on this tape become stale.
+    pub fn clear(&self) {
+        self.truncate(0, 0);
+    }
+
+    #[inline]
+    #[must_use]
+    /// Makes the tape also record what each node computes and the outcome of every
+    /// comparison made on its variables, so that it can be replayed with a
+    /// [`RecordedFunction`](crate::replay::RecordedFunction).
+    ///
+    /// Recording gets somewhat slower and uses more memory. Nodes recorded before this call, and
+    /// nodes recorded through [`Variable::apply_unary_function`] and its siblings,
+    /// [`Tape::record_external`] or [`Tape::preaccumulate`], have no replayable kind. Only
+    /// comparisons of `f32` or `f64` variables with values or variables of the same type are
+    /// recorded.
+    pub fn with_op_kinds(self) -> Self {
+        let len = self.len();
+        self.ops.borrow_mut().get_or_insert_with(|| OpLog::new(len));
+        self.records_ops.set(true);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns `true` if the tape records operation kinds.
+    pub fn records_op_kinds(&self) -> bool {
+        self.records_ops.get()
+    }
+
+    #[cfg(feature = "serde")]
+    /// Starts recording operation kinds with `log` as the kinds and comparisons so far.
+    pub(crate) fn set_op_log(&self, log: OpLog<F>) {
+        *self.ops.borrow_mut() = Some(log);
+        self.records_ops.set(true);
+    }
+
+    fn truncate(&self, len: usize, branches: usize) {
+        self.operations.borrow_mut().truncate(len);
+        if let Some(log) = self.ops.borrow_mut().as_mut() {
+            log.truncate(len, branches);
+        }
+        let mut externals = self.externals.borrow_mut();
+        let kept = externals.partition_point(|external| external.first < len);
+        externals.truncate(kept);
+        if self
+            .storage_error
+            .get()
+            .is_some_and(|(index, _)| index >= len)
+        {
+            self.storage_error.set(None);
+        }
+        let generation = self.generation.get();
+        let mut rewinds = self.rewinds.borrow_mut();
+        while rewinds.last().is_some_and(|&(_, l)| l >= len) {
+            rewinds.pop();
+        }
+        rewinds.push((generation, len));
+        self.generation.set(generation + 1);
+    }
+
+    /// Returns `true` if the first `len` nodes seen by `generation` are still recorded.
+    fn is_live_len(&self, len: usize, generation: usize) -> bool {
+        let rewinds = self.rewinds.borrow();
+        let first = rewinds.partition_point(|&(g, _)| g < generation);
+        rewinds.get(first).is_none_or(|&(_, l)| len <= l)
+    }
+
+    #[inline]
+    /// Returns `true` if the node at `index` recorded in `generation` has not been rewound.
+    pub(crate) fn is_live(&self, index: usize, generation: usize) -> bool {
+        self.is_live_len(index + 1, generation)
+    }
+
+    #[inline]
+    /// Notes that the node at `index` recorded in `generation` is an operand of the next node,
+    /// which then inherits `generation` if the operand has been rewound.
+    pub(crate) fn read_operand(&self, index: usize, generation: usize) {
+        // Every rewind starts a new generation, so nothing of the current one was rewound.
+        if generation != self.generation.get() && !self.is_live(index, generation) {
+            self.stale.set(Some(generation));
+        }
+    }
+
+    #[inline]
+    /// Flags a comparison between `lhs` and `rhs` that had the outcome `test`.
+    ///
+    /// Comparisons of stale variables are not flagged, since their nodes may belong to others.
+    pub(crate) fn record_branch(&self, lhs: Operand<F>, rhs: Operand<F>, test: BranchTest) {
+        if self.stale.take().is_some() {
+            return;
+        }
+        if let Some(log) = self.ops.borrow_mut().as_mut() {
+            log.push_branch(Branch {
+                position: self.len(),
+                lhs,
+                rhs,
+                test,
+            });
+        }
+    }
+
+    #[inline]
+    /// Panics in debug builds if `other` is not this tape.
+    pub(crate) fn debug_assert_same(&self, other: &Self) {
+        debug_assert!(
+            std::ptr::eq(self, other),
+            "cannot combine variables recorded on different tapes"
+        );
+    }
+
+    /// Returns the index of `var` after checking it can seed a sweep over this tape.
+    pub(crate) fn seed_index(&self, var: &Variable<'_, F>) -> Result<usize, GradientError> {
+        let (index, tape) = var.index.ok_or(GradientError::MissingIndex)?;
+        if !std::ptr::eq(self, tape) {
+            return Err(GradientError::TapeMismatch);
+        }
+        if !self.is_live(index, var.generation) {
+            return Err(GradientError::StaleVariable(index));
+        }
+        if index >= self.len() {
+            return Err(GradientError::StorageFull(index));
         }
+        Ok(index)
     }
 }
 
-impl<F: Copy + Zero> Tape<F> {
+impl<F: Copy> Tape<F> {
     #[inline]
-    pub fn create_variable(&self, value: F) -> Variable<F> {
+    #[must_use]
+    /// Creates a tape that records its nodes into `storage`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    /// use aad::storage::ChunkedStorage;
+    ///
+    /// let tape = Tape::with_storage(ChunkedStorage::new(4096));
+    /// let x = tape.create_variable(2.0_f64);
+    /// let y = x * x;
+    /// assert_eq!(y.compute_gradients().unwrap().get_gradient(&x), Ok(4.0));
+    /// ```
+    pub fn with_storage(storage: impl TapeStorage<F> + Send + 'static) -> Self {
+        Self {
+            operations: RefCell::new(Storage::Custom(Box::new(storage))),
+            generation: Cell::new(0),
+            rewinds: RefCell::new(Vec::new()),
+            externals: RefCell::new(Vec::new()),
+            ops: RefCell::new(None),
+            records_ops: Cell::new(false),
+            storage_error: Cell::new(None),
+            stale: Cell::new(None),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the first error the storage failed to record a node with, if any.
+    ///
+    /// Variables created after a failure keep their values, but sweeps seeded with them return
+    /// `GradientError::StorageFull`, so checking this after recording tells whether the whole
+    /// computation was taped. The error is cleared once the tape is rewound to before the node
+    /// that could not be recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    /// use aad::storage::{ChunkedStorage, StorageError};
+    ///
+    /// let tape = Tape::with_storage(ChunkedStorage::new(16).with_node_limit(2));
+    /// let mark = tape.mark();
+    /// let x = tape.create_variable(2.0_f64);
+    /// let _ = (x * x).sin();
+    /// assert_eq!(tape.storage_error(), Some(StorageError::LimitReached(2)));
+    /// tape.rewind_to(mark);
+    /// assert_eq!(tape.storage_error(), None);
+    /// ```
+    pub fn storage_error(&self) -> Option<StorageError> {
+        self.storage_error.get().map(|(_, error)| error)
+    }
+
+    #[inline]
+    /// Returns a variable referring to the node already recorded at `index`, carrying `value`.
+    ///
+    /// The tape does not keep the values of its nodes, so `value` only matters to operations
+    /// recorded from the returned variable. This recovers the variables of a tape that was
+    /// not recorded in this process, such as one loaded from a file, to sweep from them.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the node on the tape
+    /// * `value` - Value the variable carries
+    ///
+    /// # Errors
+    ///
+    /// Returns `GradientError::OutOfBounds` if no node is recorded at `index`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let x = tape.create_variable(3.0_f64);
+    /// let _ = x * x;
+    /// let y = tape.variable_at(1, 9.0).unwrap();
+    /// assert_eq!(y.compute_gradients().unwrap().get_gradient(&x), Ok(6.0));
+    /// ```
+    pub fn variable_at(&self, index: usize, value: F) -> Result<Variable<'_, F>, GradientError> {
+        let len = self.len();
+        if index >= len {
+            return Err(GradientError::OutOfBounds(index, len));
+        }
+        Ok(Variable {
+            index: Some((index, self)),
+            generation: self.generation.get(),
+            value,
+            brand: PhantomData,
+        })
+    }
+
+    #[inline]
+    /// Records a node computed by `kind`, with `constant` as its `(position, value)` constant
+    /// operand if it has one.
+    pub(crate) fn record(
+        &self,
+        value: F,
+        operation: OperationRecord<F>,
+        kind: OpKind,
+        constant: Option<(usize, F)>,
+    ) -> Variable<'_, F> {
+        let [(i, a), (j, b)] = operation.0;
+        let constants = constant.as_slice();
+        match (i, j) {
+            (usize::MAX, usize::MAX) => self.push(value, &[], kind, constants),
+            (i, usize::MAX) => self.push(value, &[(to_u32(i), a)], kind, constants),
+            (usize::MAX, j) => self.push(value, &[(to_u32(j), b)], kind, constants),
+            (i, j) => self.push(value, &[(to_u32(i), a), (to_u32(j), b)], kind, constants),
+        }
+    }
+
+    #[inline]
+    /// Records a node with an arbitrary number of `(index, partial)` parents.
+    pub(crate) fn record_nary(
+        &self,
+        value: F,
+        parents: &[(usize, F)],
+        kind: OpKind,
+        constants: &[(usize, F)],
+    ) -> Variable<'_, F> {
+        let parents = parents
+            .iter()
+            .map(|&(idx, partial)| (to_u32(idx), partial))
+            .collect::<Vec<_>>();
+        self.push(value, &parents, kind, constants)
+    }
+
+    // Every overloaded operator ends here; without forcing it, the storage dispatch keeps it
+    // from being inlined into them and recording slows down by a third.
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    /// Appends a node to the storage.
+    ///
+    /// If the storage is full, the returned variable keeps its value but refers to the index
+    /// the node would have taken, which sweeps reject with `GradientError::StorageFull`, and
+    /// the first such error is kept for [`Tape::storage_error`].
+    ///
+    /// If an operand was stale, the returned variable is stale under its generation.
+    fn push(
+        &self,
+        value: F,
+        parents: &[(u32, F)],
+        kind: OpKind,
+        constants: &[(usize, F)],
+    ) -> Variable<'_, F> {
+        let mut operations = self.operations.borrow_mut();
+        let index = operations.len();
+        match operations.push(parents) {
+            Ok(()) => {
+                if let Some(log) = self.ops.borrow_mut().as_mut() {
+                    log.push(kind, constants);
+                }
+            }
+            Err(error) => {
+                if self.storage_error.get().is_none() {
+                    self.storage_error.set(Some((index, error)));
+                }
+            }
+        }
         Variable {
-            index: {
-                let mut operations = self.operations.borrow_mut();
-                let count = (*operations).len();
-                (*operations).push(OperationRecord([
-                    (usize::MAX, F::zero()),
-                    (usize::MAX, F::zero()),
-                ]));
-                Some((count, self))
-            },
+            index: Some((index, self)),
+            generation: self.stale.take().unwrap_or_else(|| self.generation.get()),
             value,
+            brand: PhantomData,
         }
     }
+}
+
+impl<F: Copy + Zero> Tape<F> {
+    #[inline]
+    pub fn create_variable(&self, value: F) -> Variable<'_, F> {
+        self.record(
+            value,
+            OperationRecord([(usize::MAX, F::zero()), (usize::MAX, F::zero())]),
+            OpKind::Input,
+            Some((0, value)),
+        )
+    }
 
     #[inline]
-    pub fn create_variables<const N: usize>(&self, values: &[F; N]) -> [Variable<F>; N] {
+    pub fn create_variables<const N: usize>(&self, values: &[F; N]) -> [Variable<'_, F>; N] {
         std::array::from_fn(|i| self.create_variable(values[i]))
     }
 
     #[inline]
-    pub fn create_variables_iter(&self, values: &[F]) -> impl Iterator<Item = Variable<F>> {
+    pub fn create_variables_iter(&self, values: &[F]) -> impl Iterator<Item = Variable<'_, F>> {
         values.iter().map(|value| self.create_variable(*value))
     }
+
+    #[inline]
+    /// Records a black-box routine that maps `inputs` to outputs with the given `values`.
+    ///
+    /// The routine itself is not taped. Instead, `adjoint` receives the adjoints of the outputs
+    /// during every sweep that reaches them and must return the adjoints of the inputs, that is
+    /// the vector-Jacobian product `w^T * J` of the routine for the output adjoints `w`.
+    /// Forward sweeps ([`Tape::jvp`]) rebuild the local Jacobian from one callback per output.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Variables the routine depends on; constants receive no adjoint
+    /// * `values` - Values of the routine's outputs
+    /// * `adjoint` - Callback mapping output adjoints to one adjoint per input
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Variable<F>>` - One variable per output value, recorded on this tape
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+    /// let (a, b) = (x.value(), y.value());
+    /// let outputs = tape.record_external(&[x, y], &[a * b, a + b], move |w| {
+    ///     vec![w[0] * b + w[1], w[0] * a + w[1]]
+    /// });
+    /// let grads = (outputs[0] + outputs[1]).compute_gradients().unwrap();
+    /// assert_eq!(grads.get_gradients(&[x, y]).unwrap(), [4.0, 3.0]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// A sweep panics if `adjoint` does not return exactly one adjoint per input.
+    pub fn record_external(
+        &self,
+        inputs: &[Variable<'_, F>],
+        values: &[F],
+        adjoint: impl Fn(&[F]) -> Vec<F> + Send + 'static,
+    ) -> Vec<Variable<'_, F>> {
+        let inputs = inputs
+            .iter()
+            .map(|input| match input.live_index() {
+                Some((index, tape)) => {
+                    self.debug_assert_same(tape);
+                    index
+                }
+                None => usize::MAX,
+            })
+            .collect();
+        let stale = self.stale.take();
+        let first = self.len();
+        self.externals.borrow_mut().push(ExternalRecord::new(
+            first,
+            values.len(),
+            inputs,
+            Box::new(adjoint),
+        ));
+        values
+            .iter()
+            .map(|&value| {
+                let output = self.push(value, &[], OpKind::Opaque, &[]);
+                Variable {
+                    generation: stale.unwrap_or(output.generation),
+                    ..output
+                }
+            })
+            .collect()
+    }
+}
+
+impl<F: Copy + One + Zero + Send + 'static> Tape<F> {
+    /// Records `f` applied to `inputs` as a single routine whose intermediate operations are
+    /// not kept on the tape.
+    ///
+    /// `f` is first evaluated on constants, which records nothing, and only its inputs are
+    /// stored. Whenever a reverse sweep reaches its outputs, `f` is evaluated again on a
+    /// temporary tape and swept there to obtain the adjoints of `inputs`. Memory use then
+    /// stays at one node per input and output, at the cost of evaluating `f` twice per sweep,
+    /// which suits long time-stepping loops. Forward sweeps ([`Tape::jvp`]) re-run `f` once per
+    /// output.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - Variables `f` depends on; constants receive no adjoint
+    /// * `f` - Function mapping its inputs to its outputs, for variables of any tape. It is kept
+    ///   on the tape for later sweeps, so it must be `'static` and `Send`: it cannot borrow data
+    ///   such as a market or a time grid, which it has to own or share through an `Arc`
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Variable<F>>` - One variable per output of `f`, recorded on this tape
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let [x, y] = tape.create_variables(&[0.5_f64, 1.5]);
+    /// let outputs = tape.checkpoint(&[x, y], |inputs| vec![(inputs[0] * inputs[1]).sin()]);
+    /// assert_eq!(tape.len(), 3);
+    /// let grads = outputs[0].compute_gradients().unwrap();
+    /// assert_eq!(grads.get_gradient(&x), Ok(1.5 * 0.75_f64.cos()));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// A sweep panics if `f` returns a variable recorded on a tape other than the one it was
+    /// given, or a different number of outputs than in the first evaluation.
+    pub fn checkpoint(
+        &self,
+        inputs: &[Variable<'_, F>],
+        f: impl for<'v> Fn(&[Variable<'v, F>]) -> Vec<Variable<'v, F>> + Send + 'static,
+    ) -> Vec<Variable<'_, F>> {
+        let values = inputs.iter().map(Variable::value).collect::<Vec<_>>();
+        let constants = values
+            .iter()
+            .map(|&value| Variable::constant(value))
+            .collect::<Vec<_>>();
+        let outputs = f(&constants)
+            .iter()
+            .map(Variable::value)
+            .collect::<Vec<_>>();
+        self.record_external(inputs, &outputs, move |adjoints| {
+            let inner = Tape::new();
+            let inputs = inner.create_variables_iter(&values).collect::<Vec<_>>();
+            let outputs = f(&inputs);
+            assert_eq!(
+                outputs.len(),
+                adjoints.len(),
+                "checkpointed function must return the same number of outputs on every call"
+            );
+            let seeds = outputs
+                .into_iter()
+                .zip(adjoints.iter().copied())
+                .filter(|(output, _)| output.index.is_some())
+                .collect::<Vec<_>>();
+            let grads = inner
+                .backward(&seeds)
+                .expect("checkpointed function must return variables of the tape it was given");
+            // The inputs are the first nodes of the inner tape.
+            grads.as_slice()[..inputs.len()].to_vec()
+        })
+    }
+}
+
+impl<F: Copy + One + Zero> Tape<F> {
+    #[inline]
+    /// Performs a single reverse sweep seeded with several weighted outputs.
+    ///
+    /// The result is the vector-Jacobian product `sum_k w_k * d(y_k)/d(x)` for every node `x`
+    /// on the tape, where `seeds` holds the `(y_k, w_k)` pairs. Seeding the same output twice
+    /// adds the weights.
+    ///
+    /// # Arguments
+    ///
+    /// * `seeds` - Output variables paired with their adjoint weights
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Gradients<F>)` - The accumulated gradients if successful
+    /// * `Err(GradientError)` - If any seed cannot be used
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if a seed has no index in the computation graph
+    /// * Returns `GradientError::TapeMismatch` if a seed is recorded on another tape
+    /// * Returns `GradientError::StaleVariable` if a seed was invalidated by rewinding the tape
+    pub fn backward(&self, seeds: &[(Variable<'_, F>, F)]) -> Result<Gradients<F>, GradientError> {
+        let end = self.seeded_len(seeds)?;
+        let mut grads = vec![F::zero(); self.len()];
+        self.sweep(seeds, end, &mut grads);
+        Ok(Gradients::new(
+            grads,
+            Some(std::ptr::from_ref(self).addr()),
+            Some(self.generation.get()),
+        ))
+    }
+
+    #[inline]
+    /// Performs [`Tape::backward`] into `buffer`, reusing its allocation.
+    ///
+    /// Only the adjoints the previous sweep into `buffer` could have written are reset, so
+    /// repeatedly sweeping a short segment of a long tape costs nothing for the rest of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `seeds` - Output variables paired with their adjoint weights
+    /// * `buffer` - Buffer the adjoints are accumulated in
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Gradients<F, &[F]>)` - The accumulated gradients, borrowed from `buffer`
+    /// * `Err(GradientError)` - If any seed cannot be used
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if a seed has no index in the computation graph
+    /// * Returns `GradientError::TapeMismatch` if a seed is recorded on another tape
+    /// * Returns `GradientError::StaleVariable` if a seed was invalidated by rewinding the tape
+    pub fn backward_into<'b>(
+        &self,
+        seeds: &[(Variable<'_, F>, F)],
+        buffer: &'b mut GradientBuffer<F>,
+    ) -> Result<Gradients<F, &'b [F]>, GradientError> {
+        let end = self.seeded_len(seeds)?;
+        let grads = buffer.prepare(self.len(), end);
+        self.sweep(seeds, end, grads);
+        Ok(Gradients::new(
+            grads,
+            Some(std::ptr::from_ref(self).addr()),
+            Some(self.generation.get()),
+        ))
+    }
+
+    /// Checks every seed and returns the number of nodes a sweep from them has to visit.
+    fn seeded_len(&self, seeds: &[(Variable<'_, F>, F)]) -> Result<usize, GradientError> {
+        seeds
+            .iter()
+            .try_fold(0, |end, (var, _)| Ok(end.max(self.seed_index(var)? + 1)))
+    }
+
+    /// Accumulates the adjoints of the first `end` nodes from checked `seeds` into `grads`.
+    fn sweep(&self, seeds: &[(Variable<'_, F>, F)], end: usize, grads: &mut [F]) {
+        for (var, weight) in seeds {
+            let (index, _) = var.index.expect("seeds are checked before sweeping");
+            grads[index] = grads[index] + *weight;
+        }
+
+        let operations = &self.operations.borrow();
+        let externals = self.externals.borrow();
+        let mut externals = externals
+            .iter()
+            .rev()
+            .skip_while(|external| external.first >= end)
+            .peekable();
+        operations.for_each_rev(0..end, |i, indices, partials| {
+            if let Some(external) = externals.next_if(|external| external.first == i) {
+                let outputs = &grads[i..i + external.outputs];
+                if !outputs.iter().all(Zero::is_zero) {
+                    let adjoints = external.adjoint(outputs);
+                    for (&idx, adjoint) in external.inputs.iter().zip(adjoints) {
+                        if idx != usize::MAX {
+                            grads[idx] = grads[idx] + adjoint;
+                        }
+                    }
+                }
+            }
+            let grad = grads[i];
+            if grad.is_zero() {
+                return;
+            }
+            for (&idx, &val) in indices.iter().zip(partials) {
+                let idx = idx as usize;
+                grads[idx] = grads[idx] + val * grad;
+            }
+        });
+    }
+
+    #[inline]
+    /// Computes the derivatives of `output` with respect to `inputs`, consuming the tape.
+    ///
+    /// The reverse sweep releases the recorded nodes as it passes them, and allocates adjoints
+    /// in blocks that are dropped once the sweep is past them, so memory use falls as the sweep
+    /// proceeds instead of peaking at the whole tape plus a full-length adjoint vector. When it
+    /// returns, the tape is empty as after [`Tape::clear`] and every variable recorded on it is
+    /// stale.
+    ///
+    /// The tape is consumed through `&self` rather than by value: `output` and `inputs` borrow
+    /// the tape, so a method taking `self` could not be called with them. Clearing instead keeps
+    /// the tape usable for the next recording, and any later use of the drained variables
+    /// returns `GradientError::StaleVariable`.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Variable to differentiate
+    /// * `inputs` - Variables the derivatives are taken with respect to
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<F>)` - The derivative of `output` with respect to each of `inputs`, in order
+    /// * `Err(GradientError)` - If the output or an input cannot be used, leaving the tape intact
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if a variable has no index in the computation graph
+    /// * Returns `GradientError::TapeMismatch` if a variable is recorded on another tape
+    /// * Returns `GradientError::StaleVariable` if a variable was invalidated by rewinding the tape
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+    /// let z = x * y + x.sin();
+    /// let grads = tape.drain_gradients(&z, &[x, y]).unwrap();
+    /// assert_eq!(grads, [3.0 + 2.0_f64.cos(), 2.0]);
+    /// assert!(tape.is_empty());
+    /// ```
+    pub fn drain_gradients(
+        &self,
+        output: &Variable<'_, F>,
+        inputs: &[Variable<'_, F>],
+    ) -> Result<Vec<F>, GradientError> {
+        self.drain_gradients_by(output, inputs, DRAIN_STEP)
+    }
+
+    /// [`Tape::drain_gradients`], releasing the tape `step` nodes at a time.
+    fn drain_gradients_by(
+        &self,
+        output: &Variable<'_, F>,
+        inputs: &[Variable<'_, F>],
+        step: usize,
+    ) -> Result<Vec<F>, GradientError> {
+        let mut pending = inputs
+            .iter()
+            .enumerate()
+            .map(|(k, input)| self.seed_index(input).map(|index| (index, k)))
+            .collect::<Result<Vec<_>, _>>()?;
+        pending.sort_unstable();
+        let end = self.seed_index(output)? + 1;
+        // Inputs recorded after the output cannot affect it, and keep a zero derivative.
+        while pending.last().is_some_and(|&(index, _)| index >= end) {
+            pending.pop();
+        }
+        let mut adjoints = Adjoints::new(end);
+        adjoints.add(end - 1, F::one());
+        let mut result = vec![F::zero(); inputs.len()];
+
+        let externals = self.externals.take();
+        let mut externals = externals
+            .iter()
+            .rev()
+            .skip_while(|external| external.first >= end)
+            .peekable();
+        let mut high = end;
+        while high > 0 {
+            let low = high.saturating_sub(step);
+            self.operations
+                .borrow()
+                .for_each_rev(low..high, |i, indices, partials| {
+                    if let Some(external) = externals.next_if(|external| external.first == i) {
+                        let outputs = (i..i + external.outputs)
+                            .map(|o| if o < end { adjoints.get(o) } else { F::zero() })
+                            .collect::<Vec<_>>();
+                        if !outputs.iter().all(Zero::is_zero) {
+                            let input_adjoints = external.adjoint(&outputs);
+                            for (&idx, adjoint) in external.inputs.iter().zip(input_adjoints) {
+                                if idx != usize::MAX {
+                                    adjoints.add(idx, adjoint);
+                                }
+                            }
+                        }
+                    }
+                    let grad = adjoints.get(i);
+                    if grad.is_zero() {
+                        return;
+                    }
+                    for (&idx, &val) in indices.iter().zip(partials) {
+                        adjoints.add(idx as usize, val * grad);
+                    }
+                });
+            while let Some(&(index, k)) = pending.last().filter(|&&(index, _)| index >= low) {
+                result[k] = adjoints.get(index);
+                pending.pop();
+            }
+            // The outputs of an external straddling `low` are read when the sweep reaches its
+            // first output, so their adjoints must outlive this step.
+            let kept = externals
+                .peek()
+                .map_or(low, |external| low.max(external.first + external.outputs));
+            self.operations.borrow_mut().release(low);
+            adjoints.release(kept);
+            high = low;
+        }
+        self.clear();
+        Ok(result)
+    }
+
+    #[inline]
+    /// Computes the derivatives of `output` with respect to `inputs`, visiting only the nodes
+    /// `output` depends on.
+    ///
+    /// [`Tape::backward`] walks every node below the output and only skips the arithmetic of
+    /// those whose adjoint is zero. This sweep follows the parents of the nodes it visits
+    /// instead, so nodes of unrelated computations recorded on the same tape are never reached,
+    /// and it stops as soon as no node above the lowest of `inputs` is left, since every
+    /// requested adjoint is then final. Each visited node costs a priority queue operation, so
+    /// this pays off when `output` depends on a small part of the tape, such as one trade of a
+    /// portfolio. The results are identical to those of [`Tape::backward`].
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Variable to differentiate
+    /// * `inputs` - Variables the derivatives are taken with respect to
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<F>)` - The derivative of `output` with respect to each of `inputs`, in order
+    /// * `Err(GradientError)` - If the output or an input cannot be used
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if a variable has no index in the computation graph
+    /// * Returns `GradientError::TapeMismatch` if a variable is recorded on another tape
+    /// * Returns `GradientError::StaleVariable` if a variable was invalidated by rewinding the tape
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+    /// let z = x * y;
+    /// let _unrelated = (y.exp() + 1.0).ln();
+    /// let w = z.sin() + x;
+    /// let grads = tape.pruned_gradients(&w, &[x, y]).unwrap();
+    /// assert_eq!(grads, [3.0 * 6.0_f64.cos() + 1.0, 2.0 * 6.0_f64.cos()]);
+    /// ```
+    pub fn pruned_gradients(
+        &self,
+        output: &Variable<'_, F>,
+        inputs: &[Variable<'_, F>],
+    ) -> Result<Vec<F>, GradientError> {
+        let indices = inputs
+            .iter()
+            .map(|input| self.seed_index(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        let lowest = indices.iter().copied().min().unwrap_or(usize::MAX);
+        let end = self.seed_index(output)? + 1;
+        let mut adjoints = Adjoints::new(end);
+        adjoints.add(end - 1, F::one());
+
+        let operations = self.operations.borrow();
+        let externals = self.externals.borrow();
+        let mut frontier = Frontier::new(end, lowest);
+        frontier.push(end - 1);
+        while let Some(i) = frontier.pop() {
+            let external = externals
+                .get(
+                    externals
+                        .partition_point(|external| external.first <= i)
+                        .wrapping_sub(1),
+                )
+                .filter(|external| i < external.first + external.outputs);
+            if let Some(external) = external {
+                if i > external.first {
+                    // Like a full sweep, call the routine once, from its first output.
+                    frontier.push(external.first);
+                } else {
+                    let outputs = (i..i + external.outputs)
+                        .map(|o| if o < end { adjoints.get(o) } else { F::zero() })
+                        .collect::<Vec<_>>();
+                    if !outputs.iter().all(Zero::is_zero) {
+                        let input_adjoints = external.adjoint(&outputs);
+                        for (&idx, adjoint) in external.inputs.iter().zip(input_adjoints) {
+                            if idx != usize::MAX {
+                                adjoints.add(idx, adjoint);
+                                frontier.push(idx);
+                            }
+                        }
+                    }
+                }
+            }
+            let grad = adjoints.get(i);
+            if grad.is_zero() {
+                continue;
+            }
+            operations.for_each(i..i + 1, |_, indices, partials| {
+                for (&idx, &val) in indices.iter().zip(partials) {
+                    let idx = idx as usize;
+                    adjoints.add(idx, val * grad);
+                    frontier.push(idx);
+                }
+            });
+        }
+        Ok(indices
+            .into_iter()
+            .map(|index| {
+                if index < end {
+                    adjoints.get(index)
+                } else {
+                    F::zero()
+                }
+            })
+            .collect())
+    }
+
+    /// Collapses the nodes recorded since `mark` into one node per output, holding the local
+    /// Jacobian of the outputs with respect to the segment's inputs.
+    ///
+    /// The segment's inputs are the nodes recorded before `mark` that it reads. Each output is
+    /// swept back to `mark`, the segment is dropped as by [`Tape::rewind_to`], and every output
+    /// is recorded again with those inputs as parents and its derivatives towards them as
+    /// partials. Later sweeps then cost one term per input instead of one per intermediate
+    /// operation, which shrinks kernels with many operations but few inputs and outputs, and
+    /// yield the same gradients up to rounding.
+    ///
+    /// The collapsed outputs are recorded without an operation kind, so a
+    /// [`RecordedFunction`](crate::replay::RecordedFunction) cannot be built from outputs that
+    /// depend on them.
+    ///
+    /// # Arguments
+    ///
+    /// * `mark` - Start of the segment to collapse
+    /// * `outputs` - Variables of the segment used afterwards
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Variable<F>>)` - One variable per output, with the same value, replacing it;
+    ///   outputs recorded before `mark` are returned as they are
+    /// * `Err(GradientError)` - If an output cannot be used, leaving the tape intact
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if an output has no index in the computation graph
+    /// * Returns `GradientError::TapeMismatch` if an output is recorded on another tape
+    /// * Returns `GradientError::StaleVariable` if an output was invalidated by rewinding the tape
+    /// * Returns `GradientError::SegmentVariable` if an output depends on a variable created
+    ///   after `mark`, which the segment cannot keep
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+    /// let mark = tape.mark();
+    /// let mut z = x;
+    /// for _ in 0..100 {
+    ///     z = (z * y).sin() + x;
+    /// }
+    /// let expected = z.compute_gradients().unwrap().get_gradients(&[x, y]).unwrap();
+    ///
+    /// let z = tape.preaccumulate(mark, &[z]).unwrap()[0];
+    /// assert_eq!(tape.len(), 3);
+    /// let grads = z.compute_gradients().unwrap().get_gradients(&[x, y]).unwrap();
+    /// assert!((grads[0] - expected[0]).abs() < 1e-12 && (grads[1] - expected[1]).abs() < 1e-12);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mark` was taken after a point the tape has since been rewound past.
+    pub fn preaccumulate(
+        &self,
+        mark: TapeMark,
+        outputs: &[Variable<'_, F>],
+    ) -> Result<Vec<Variable<'_, F>>, GradientError> {
+        assert!(
+            self.is_live_len(mark.len, mark.generation),
+            "tape mark has been invalidated by an earlier rewind"
+        );
+        let indices = outputs
+            .iter()
+            .map(|output| self.seed_index(output))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rows = indices
+            .iter()
+            .map(|&index| {
+                (index >= mark.len)
+                    .then(|| self.segment_partials(mark.len, index))
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.rewind_to(mark);
+        Ok(outputs
+            .iter()
+            .zip(indices)
+            .zip(rows)
+            .map(|((output, index), row)| match row {
+                Some(parents) => self.record_nary(output.value, &parents, OpKind::Opaque, &[]),
+                None => Variable {
+                    index: Some((index, self)),
+                    generation: self.generation.get(),
+                    value: output.value,
+                    brand: PhantomData,
+                },
+            })
+            .collect())
+    }
+
+    /// Sweeps `output` back to `start`, returning its nonzero derivatives towards the nodes
+    /// before `start`, by increasing index.
+    fn segment_partials(
+        &self,
+        start: usize,
+        output: usize,
+    ) -> Result<Vec<(usize, F)>, GradientError> {
+        let mut adjoints = vec![F::zero(); output + 1 - start];
+        adjoints[output - start] = F::one();
+        let mut row = BTreeMap::new();
+        let mut add = |adjoints: &mut [F], idx: usize, value: F| {
+            if idx < start {
+                let partial = row.entry(idx).or_insert_with(F::zero);
+                *partial = *partial + value;
+            } else {
+                adjoints[idx - start] = adjoints[idx - start] + value;
+            }
+        };
+
+        let mut leaf = None;
+        let externals = self.externals.borrow();
+        let mut externals = externals
+            .iter()
+            .rev()
+            .skip_while(|external| external.first > output)
+            .peekable();
+        self.operations
+            .borrow()
+            .for_each_rev(start..output + 1, |i, indices, partials| {
+                let external = externals
+                    .peek()
+                    .is_some_and(|external| i < external.first + external.outputs);
+                if let Some(external) = externals.next_if(|external| external.first == i) {
+                    let outputs = (i..i + external.outputs)
+                        .map(|o| adjoints.get(o - start).copied().unwrap_or_else(F::zero))
+                        .collect::<Vec<_>>();
+                    if !outputs.iter().all(Zero::is_zero) {
+                        let input_adjoints = external.adjoint(&outputs);
+                        for (&idx, adjoint) in external.inputs.iter().zip(input_adjoints) {
+                            if idx != usize::MAX {
+                                add(&mut adjoints, idx, adjoint);
+                            }
+                        }
+                    }
+                }
+                let grad = adjoints[i - start];
+                if grad.is_zero() {
+                    return;
+                }
+                if indices.is_empty() && !external {
+                    leaf = Some(i);
+                }
+                for (&idx, &val) in indices.iter().zip(partials) {
+                    add(&mut adjoints, idx as usize, val * grad);
+                }
+            });
+        if let Some(leaf) = leaf {
+            return Err(GradientError::SegmentVariable(leaf));
+        }
+        Ok(row
+            .into_iter()
+            .filter(|(_, partial)| !partial.is_zero())
+            .collect())
+    }
+
+    #[inline]
+    /// Computes the Jacobian of `outputs` with respect to `inputs` in a single reverse sweep.
+    ///
+    /// One adjoint per output is carried through each node, so the recorded operations are
+    /// traversed once regardless of the number of outputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `outputs` - Variables whose derivatives form the rows of the Jacobian
+    /// * `inputs` - Variables the derivatives are taken with respect to, forming the columns
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Vec<F>>)` - Row-major Jacobian where `jacobian[k][l]` is `d(outputs[k])/d(inputs[l])`
+    /// * `Err(GradientError)` - If any output or input cannot be used
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if a variable has no index in the computation graph
+    /// * Returns `GradientError::TapeMismatch` if a variable is recorded on another tape
+    /// * Returns `GradientError::StaleVariable` if a variable was invalidated by rewinding the tape
+    pub fn jacobian(
+        &self,
+        outputs: &[Variable<'_, F>],
+        inputs: &[Variable<'_, F>],
+    ) -> Result<Vec<Vec<F>>, GradientError> {
+        let input_indices = inputs
+            .iter()
+            .map(|input| self.seed_index(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        let operations = &self.operations.borrow();
+        let m = outputs.len();
+        let mut grads = vec![F::zero(); operations.len() * m];
+        let mut end = 0;
+        for (k, output) in outputs.iter().enumerate() {
+            let index = self.seed_index(output)?;
+            grads[index * m + k] = grads[index * m + k] + F::one();
+            end = end.max(index + 1);
+        }
+
+        let externals = self.externals.borrow();
+        let mut externals = externals
+            .iter()
+            .rev()
+            .skip_while(|external| external.first >= end)
+            .peekable();
+        operations.for_each_rev(0..end, |i, indices, partials| {
+            if let Some(external) = externals.next_if(|external| external.first == i) {
+                for k in 0..m {
+                    let outputs = (i..i + external.outputs)
+                        .map(|o| grads[o * m + k])
+                        .collect::<Vec<_>>();
+                    if outputs.iter().all(Zero::is_zero) {
+                        continue;
+                    }
+                    let adjoints = external.adjoint(&outputs);
+                    for (&idx, adjoint) in external.inputs.iter().zip(adjoints) {
+                        if idx != usize::MAX {
+                            grads[idx * m + k] = grads[idx * m + k] + adjoint;
+                        }
+                    }
+                }
+            }
+            let (head, tail) = grads.split_at_mut(i * m);
+            let grad = &tail[..m];
+            if grad.iter().all(Zero::is_zero) {
+                return;
+            }
+            for (&idx, &val) in indices.iter().zip(partials) {
+                let idx = idx as usize;
+                let parent = &mut head[idx * m..(idx + 1) * m];
+                for (p, g) in parent.iter_mut().zip(grad) {
+                    *p = *p + val * *g;
+                }
+            }
+        });
+
+        Ok((0..m)
+            .map(|k| {
+                input_indices
+                    .iter()
+                    .map(|&index| grads[index * m + k])
+                    .collect()
+            })
+            .collect())
+    }
+
+    #[inline]
+    /// Computes a Jacobian-vector product with a forward (tangent-linear) sweep over the tape.
+    ///
+    /// The recorded partials are replayed from the seeded variables towards the outputs, so
+    /// no user code is re-run. A seed on an intermediate variable is added to the tangent it
+    /// receives from its own inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `seeds` - Variables paired with their tangent (direction) values
+    /// * `outputs` - Variables whose directional derivatives are returned
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<F>)` - The tangent of each output, in the order of `outputs`
+    /// * `Err(GradientError)` - If any seed or output cannot be used
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if a variable has no index in the computation graph
+    /// * Returns `GradientError::TapeMismatch` if a variable is recorded on another tape
+    /// * Returns `GradientError::StaleVariable` if a variable was invalidated by rewinding the tape
+    pub fn jvp(
+        &self,
+        seeds: &[(Variable<'_, F>, F)],
+        outputs: &[Variable<'_, F>],
+    ) -> Result<Vec<F>, GradientError> {
+        let output_indices = outputs
+            .iter()
+            .map(|output| self.seed_index(output))
+            .collect::<Result<Vec<_>, _>>()?;
+        let operations = &self.operations.borrow();
+        let mut tangents = vec![F::zero(); operations.len()];
+        let mut start = operations.len();
+        for (var, tangent) in seeds {
+            let index = self.seed_index(var)?;
+            tangents[index] = tangents[index] + *tangent;
+            start = start.min(index);
+        }
+        let end = output_indices.iter().max().map_or(0, |&index| index + 1);
+
+        let externals = self.externals.borrow();
+        let mut externals = externals
+            .iter()
+            .skip_while(|external| external.first < start)
+            .peekable();
+        operations.for_each(start..end, |i, indices, partials| {
+            if let Some(external) = externals.next_if(|external| external.first == i) {
+                let input_tangents = external
+                    .inputs
+                    .iter()
+                    .map(|&idx| {
+                        if idx == usize::MAX {
+                            F::zero()
+                        } else {
+                            tangents[idx]
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                if !input_tangents.iter().all(Zero::is_zero) {
+                    // Each output's row of the local Jacobian is the adjoint of a unit seed.
+                    let mut seed = vec![F::zero(); external.outputs];
+                    for o in 0..external.outputs {
+                        seed[o] = F::one();
+                        let row = external.adjoint(&seed);
+                        seed[o] = F::zero();
+                        for (d, t) in row.into_iter().zip(&input_tangents) {
+                            tangents[i + o] = tangents[i + o] + d * *t;
+                        }
+                    }
+                }
+            }
+            let mut tangent = tangents[i];
+            for (&idx, &val) in indices.iter().zip(partials) {
+                tangent = tangent + val * tangents[idx as usize];
+            }
+            tangents[i] = tangent;
+        });
+
+        Ok(output_indices
+            .into_iter()
+            .map(|index| tangents[index])
+            .collect())
+    }
+}
+
+/// Adjoints of a consuming sweep, allocated in blocks on first use.
+struct Adjoints<F> {
+    blocks: Vec<Vec<F>>,
+}
+
+impl<F: Copy + Zero> Adjoints<F> {
+    fn new(len: usize) -> Self {
+        Self {
+            blocks: (0..len.div_ceil(ADJOINT_BLOCK))
+                .map(|_| Vec::new())
+                .collect(),
+        }
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> F {
+        self.blocks[index / ADJOINT_BLOCK]
+            .get(index % ADJOINT_BLOCK)
+            .copied()
+            .unwrap_or_else(F::zero)
+    }
+
+    #[inline]
+    fn add(&mut self, index: usize, value: F) {
+        let block = &mut self.blocks[index / ADJOINT_BLOCK];
+        if block.is_empty() {
+            *block = vec![F::zero(); ADJOINT_BLOCK];
+        }
+        let adjoint = &mut block[index % ADJOINT_BLOCK];
+        *adjoint = *adjoint + value;
+    }
+
+    /// Drops the blocks holding only adjoints at or after `len`.
+    fn release(&mut self, len: usize) {
+        self.blocks.truncate(len.div_ceil(ADJOINT_BLOCK));
+    }
+}
+
+/// Nodes a pruned sweep has yet to visit, popped from the highest index down.
+struct Frontier {
+    queue: BinaryHeap<usize>,
+    /// One bit per node, set once the node is queued.
+    queued: Vec<u64>,
+    /// Nodes at or below this index cannot change a requested adjoint and are never queued.
+    lowest: usize,
+}
+
+impl Frontier {
+    fn new(len: usize, lowest: usize) -> Self {
+        Self {
+            queue: BinaryHeap::new(),
+            queued: vec![0; len.div_ceil(64)],
+            lowest,
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, index: usize) {
+        let (word, bit) = (index / 64, 1 << (index % 64));
+        if index > self.lowest && self.queued[word] & bit == 0 {
+            self.queued[word] |= bit;
+            self.queue.push(index);
+        }
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<usize> {
+        self.queue.pop()
+    }
+}
+
+impl<F> BrandedTape<'_, F> {
+    #[inline]
+    #[must_use]
+    /// Returns the number of nodes currently recorded on the tape.
+    pub fn len(self) -> usize {
+        self.tape.len()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns `true` if no nodes are recorded on the tape.
+    pub fn is_empty(self) -> bool {
+        self.tape.is_empty()
+    }
+
+    #[inline]
+    #[must_use]
+    /// See [`Tape::mark`].
+    pub fn mark(self) -> TapeMark {
+        self.tape.mark()
+    }
+
+    #[inline]
+    /// See [`Tape::rewind_to`].
+    pub fn rewind_to(self, mark: TapeMark) {
+        self.tape.rewind_to(mark);
+    }
+
+    #[inline]
+    /// See [`Tape::clear`].
+    pub fn clear(self) {
+        self.tape.clear();
+    }
+}
+
+impl<'id, F: Copy + One + Zero> BrandedTape<'id, F> {
+    #[inline]
+    /// See [`Tape::backward`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Tape::backward`].
+    pub fn backward(self, seeds: &[(Variable<'id, F>, F)]) -> Result<Gradients<F>, GradientError> {
+        self.tape.backward(seeds)
+    }
+}
+
+impl<'id, F: Copy + One + Zero> BrandedTape<'id, F> {
+    #[inline]
+    /// See [`Tape::backward_into`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Tape::backward_into`].
+    pub fn backward_into<'b>(
+        self,
+        seeds: &[(Variable<'id, F>, F)],
+        buffer: &'b mut GradientBuffer<F>,
+    ) -> Result<Gradients<F, &'b [F]>, GradientError> {
+        self.tape.backward_into(seeds, buffer)
+    }
+}
+
+impl<'id, F: Copy + One + Zero> BrandedTape<'id, F> {
+    #[inline]
+    /// See [`Tape::drain_gradients`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Tape::drain_gradients`].
+    pub fn drain_gradients(
+        self,
+        output: &Variable<'id, F>,
+        inputs: &[Variable<'id, F>],
+    ) -> Result<Vec<F>, GradientError> {
+        self.tape.drain_gradients(output, inputs)
+    }
+}
+
+impl<'id, F: Copy + One + Zero> BrandedTape<'id, F> {
+    #[inline]
+    /// See [`Tape::pruned_gradients`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Tape::pruned_gradients`].
+    pub fn pruned_gradients(
+        self,
+        output: &Variable<'id, F>,
+        inputs: &[Variable<'id, F>],
+    ) -> Result<Vec<F>, GradientError> {
+        self.tape.pruned_gradients(output, inputs)
+    }
+}
+
+impl<'id, F: Copy + One + Zero> BrandedTape<'id, F> {
+    #[inline]
+    /// See [`Tape::preaccumulate`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Tape::preaccumulate`].
+    pub fn preaccumulate(
+        self,
+        mark: TapeMark,
+        outputs: &[Variable<'id, F>],
+    ) -> Result<Vec<Variable<'id, F>>, GradientError> {
+        self.tape.preaccumulate(mark, outputs)
+    }
+}
+
+impl<'id, F: Copy + One + Zero> BrandedTape<'id, F> {
+    #[inline]
+    /// See [`Tape::jacobian`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Tape::jacobian`].
+    pub fn jacobian(
+        self,
+        outputs: &[Variable<'id, F>],
+        inputs: &[Variable<'id, F>],
+    ) -> Result<Vec<Vec<F>>, GradientError> {
+        self.tape.jacobian(outputs, inputs)
+    }
+}
+
+impl<'id, F: Copy + One + Zero> BrandedTape<'id, F> {
+    #[inline]
+    /// See [`Tape::jvp`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Tape::jvp`].
+    pub fn jvp(
+        self,
+        seeds: &[(Variable<'id, F>, F)],
+        outputs: &[Variable<'id, F>],
+    ) -> Result<Vec<F>, GradientError> {
+        self.tape.jvp(seeds, outputs)
+    }
+}
+
+impl<'id, F: Copy + Zero> BrandedTape<'id, F> {
+    #[inline]
+    pub fn create_variable(self, value: F) -> Variable<'id, F> {
+        self.tape.create_variable(value)
+    }
+
+    #[inline]
+    pub fn create_variables<const N: usize>(self, values: &[F; N]) -> [Variable<'id, F>; N] {
+        self.tape.create_variables(values)
+    }
+
+    #[inline]
+    pub fn create_variables_iter(self, values: &[F]) -> impl Iterator<Item = Variable<'id, F>> {
+        self.tape.create_variables_iter(values)
+    }
+
+    #[inline]
+    /// See [`Tape::record_external`].
+    pub fn record_external(
+        self,
+        inputs: &[Variable<'id, F>],
+        values: &[F],
+        adjoint: impl Fn(&[F]) -> Vec<F> + Send + 'static,
+    ) -> Vec<Variable<'id, F>> {
+        self.tape.record_external(inputs, values, adjoint)
+    }
+}
+
+impl<'id, F: Copy + One + Zero + Send + 'static> BrandedTape<'id, F> {
+    #[inline]
+    /// See [`Tape::checkpoint`].
+    pub fn checkpoint(
+        self,
+        inputs: &[Variable<'id, F>],
+        f: impl for<'v> Fn(&[Variable<'v, F>]) -> Vec<Variable<'v, F>> + Send + 'static,
+    ) -> Vec<Variable<'id, F>> {
+        self.tape.checkpoint(inputs, f)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::gradients::GradientError;
     use crate::tape::Tape;
+    use crate::variable::Variable;
 
     #[test]
     fn test_create_variables() {
@@ -75,4 +1595,600 @@ mod tests {
             assert!(std::ptr::eq(variable.index.unwrap().1, &tape));
         }
     }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_scope() {
+        let grads = Tape::scope(|tape| {
+            let [x, y] = tape.create_variables(&[2.0, 3.0]);
+            let mark = tape.mark();
+            let _ = x + y;
+            tape.rewind_to(mark);
+            assert_eq!(tape.len(), 2);
+            let z = x * y.sin();
+            z.compute_gradients()
+                .unwrap()
+                .get_gradients(&[x, y])
+                .unwrap()
+        });
+        assert_eq!(grads, [3.0_f64.sin(), 2.0 * 3.0_f64.cos()]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp, clippy::many_single_char_names)]
+    fn test_backward_weighted_outputs() {
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let u = x * y;
+        let v = x.sin() + y;
+        let w = y * y;
+
+        let grads = tape.backward(&[(u, 2.0), (v, -1.0), (w, 0.5)]).unwrap();
+        let [dx, dy] = grads.get_gradients(&[x, y]).unwrap();
+        assert_eq!(dx, 2.0 * 3.0 - 2.0_f64.cos());
+        assert_eq!(dy, 2.0 * 2.0 - 1.0 + 0.5 * 2.0 * 3.0);
+
+        let single = tape.backward(&[(u, 1.0)]).unwrap();
+        let expected = u.compute_gradients().unwrap();
+        assert_eq!(
+            single.get_gradients(&[x, y]).unwrap(),
+            expected.get_gradients(&[x, y]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_backward_repeated_seed_accumulates() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0);
+        let y = x * x;
+        let grads = tape.backward(&[(y, 1.0), (y, 2.0)]).unwrap();
+        assert_eq!(grads.get_gradient(&x), Ok(12.0));
+    }
+
+    #[test]
+    fn test_backward_rejects_invalid_seeds() {
+        let tape = Tape::new();
+        let other = Tape::new();
+        let x = tape.create_variable(2.0);
+        let y = other.create_variable(3.0);
+        assert_eq!(
+            tape.backward(&[(x, 1.0), (y, 1.0)]).err(),
+            Some(GradientError::TapeMismatch)
+        );
+        assert_eq!(
+            tape.backward(&[(Variable::constant(1.0), 1.0)]).err(),
+            Some(GradientError::MissingIndex)
+        );
+        let mark = tape.mark();
+        let z = x * x;
+        tape.rewind_to(mark);
+        assert_eq!(
+            tape.backward(&[(z, 1.0)]).err(),
+            Some(GradientError::StaleVariable(1))
+        );
+    }
+
+    #[test]
+    fn test_jacobian() {
+        let tape = Tape::new();
+        let inputs = tape.create_variables(&[0.5, 2.0, 3.0]);
+        let [x, y, z] = inputs;
+        let outputs = [x * y, y.sin() * z, x + z.exp(), x * x * x, z];
+
+        let jacobian = tape.jacobian(&outputs, &inputs).unwrap();
+        assert_eq!(jacobian.len(), outputs.len());
+        for (row, output) in jacobian.iter().zip(&outputs) {
+            let expected = output.compute_gradients().unwrap();
+            assert_eq!(row, &expected.get_gradients(&inputs).unwrap());
+        }
+        assert_eq!(jacobian[4], [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_jacobian_empty() {
+        let tape = Tape::new();
+        let x = tape.create_variable(1.0);
+        assert!(tape.jacobian(&[], &[x]).unwrap().is_empty());
+        assert_eq!(tape.jacobian(&[x], &[]).unwrap(), [Vec::<f64>::new()]);
+    }
+
+    #[test]
+    fn test_jvp_matches_jacobian() {
+        let tape = Tape::new();
+        let inputs = tape.create_variables(&[0.5, 2.0]);
+        let [x, y] = inputs;
+        let outputs = [x * y, (x / y).exp(), y.ln() - x, x.powi(3)];
+        let direction = [1.5, -0.25];
+
+        let tangents = tape
+            .jvp(&[(x, direction[0]), (y, direction[1])], &outputs)
+            .unwrap();
+        let jacobian = tape.jacobian(&outputs, &inputs).unwrap();
+        for (tangent, row) in tangents.iter().zip(&jacobian) {
+            let expected = row[0] * direction[0] + row[1] * direction[1];
+            assert!((tangent - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_jvp_seed_on_intermediate() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0);
+        let u = x * x;
+        let z = u * 3.0;
+        assert_eq!(tape.jvp(&[(x, 1.0), (u, 1.0)], &[z]).unwrap(), [15.0]);
+        assert_eq!(tape.jvp(&[], &[z]).unwrap(), [0.0]);
+    }
+
+    /// Records `(x * y, x + y * y)` as an external routine with a hand-written adjoint.
+    fn external<'a>(
+        tape: &'a Tape<f64>,
+        x: Variable<'a, f64>,
+        y: Variable<'a, f64>,
+    ) -> Vec<Variable<'a, f64>> {
+        let (a, b) = (x.value, y.value);
+        tape.record_external(&[x, y], &[a * b, a + b * b], move |w| {
+            vec![w[0] * b + w[1], w[0] * a + w[1] * 2.0 * b]
+        })
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_record_external_matches_taped() {
+        let tape = Tape::new();
+        let inputs = tape.create_variables(&[2.0, 3.0]);
+        let [x, y] = inputs;
+        let outputs = external(&tape, x, y);
+        let taped = [x * y, x + y * y];
+        assert_eq!(outputs[0].value, taped[0].value);
+        assert_eq!(outputs[1].value, taped[1].value);
+
+        let z = outputs[0].sin() * outputs[1];
+        let expected = taped[0].sin() * taped[1];
+        assert_eq!(
+            z.compute_gradients()
+                .unwrap()
+                .get_gradients(&inputs)
+                .unwrap(),
+            expected
+                .compute_gradients()
+                .unwrap()
+                .get_gradients(&inputs)
+                .unwrap()
+        );
+        assert_eq!(
+            tape.jacobian(&outputs, &inputs).unwrap(),
+            tape.jacobian(&taped, &inputs).unwrap()
+        );
+        let seeds = [(x, 0.5), (y, -2.0)];
+        assert_eq!(
+            tape.jvp(&seeds, &[z]).unwrap(),
+            tape.jvp(&seeds, &[expected]).unwrap()
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_record_external_with_constant_input_and_rewind() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0);
+        let mark = tape.mark();
+        let outputs = external(&tape, x, Variable::constant(3.0));
+        let grads = outputs[1].compute_gradients().unwrap();
+        assert_eq!(grads.get_gradient(&x), Ok(1.0));
+
+        tape.rewind_to(mark);
+        let y = tape.create_variable(3.0);
+        let z = x * y;
+        let grads = z.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradients(&[x, y]).unwrap(), [3.0, 2.0]);
+    }
+
+    #[test]
+    fn test_external_records_are_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<crate::external::ExternalRecord<f64>>();
+    }
+
+    #[test]
+    #[should_panic(expected = "one adjoint per input")]
+    fn test_record_external_wrong_adjoint_count() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0);
+        let outputs = tape.record_external(&[x], &[4.0], |_| vec![]);
+        let _ = outputs[0].compute_gradients();
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_checkpoint_matches_taped() {
+        fn steps<'a>(inputs: &[Variable<'a, f64>]) -> Vec<Variable<'a, f64>> {
+            let [mut a, mut b] = [inputs[0], inputs[1]];
+            for _ in 0..100 {
+                (a, b) = ((a * b).sin() + inputs[2], (b * 0.999).cos() * a);
+            }
+            vec![a, b]
+        }
+
+        let tape = Tape::new();
+        let inputs = tape.create_variables(&[0.5, 1.5, 0.25]);
+        let outputs = steps(&inputs);
+        let values = outputs.iter().map(Variable::value).collect::<Vec<_>>();
+        let expected = tape.jacobian(&outputs, &inputs).unwrap();
+
+        tape.clear();
+        let [x, y, z] = tape.create_variables(&[0.5, 1.5, 0.25]);
+        let outputs = tape.checkpoint(&[x, Variable::constant(1.5), z], steps);
+        assert_eq!(tape.len(), 5);
+        assert_eq!(
+            outputs.iter().map(Variable::value).collect::<Vec<_>>(),
+            values
+        );
+        let jacobian = tape.jacobian(&outputs, &[x, y, z]).unwrap();
+        for (row, expected) in jacobian.iter().zip(&expected) {
+            assert!((row[0] - expected[0]).abs() < 1e-12);
+            assert_eq!(row[1], 0.0);
+            assert!((row[2] - expected[2]).abs() < 1e-12);
+        }
+        let tangents = tape.jvp(&[(x, 1.0)], &outputs).unwrap();
+        assert!((tangents[0] - expected[0][0]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_drain_gradients_matches_backward() {
+        fn record(tape: &Tape<f64>) -> [Variable<'_, f64>; 3] {
+            let [x, y] = tape.create_variables(&[0.5, 1.5]);
+            let mut z = x * y;
+            for i in 0..5000 {
+                z = if i % 1000 == 0 {
+                    let outputs = external(tape, z, y);
+                    outputs[0] * 0.5 + outputs[1] * 0.25
+                } else {
+                    (z * 0.999 + x).sin() + y
+                };
+            }
+            [x, y, z]
+        }
+
+        let tape = Tape::new();
+        let [x, y, z] = record(&tape);
+        let expected = z
+            .compute_gradients()
+            .unwrap()
+            .get_gradients(&[y, x])
+            .unwrap();
+        let len = tape.len();
+        tape.clear();
+        for step in [1, 7, 4096, len] {
+            let [x, y, z] = record(&tape);
+            assert_eq!(
+                tape.drain_gradients_by(&z, &[y, x], step),
+                Ok(expected.to_vec())
+            );
+            assert!(tape.is_empty());
+            assert_eq!(
+                x.compute_gradients().err(),
+                Some(GradientError::StaleVariable(0))
+            );
+        }
+    }
+
+    #[test]
+    #[allow(clippy::many_single_char_names)]
+    fn test_pruned_gradients_matches_backward() {
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[0.5, 1.5]);
+        let mut books = [x * y, x + y];
+        for i in 0..3000 {
+            let [a, b] = books;
+            books = if i % 1000 == 0 {
+                let outputs = external(&tape, a, y);
+                [outputs[0] * 0.5 + outputs[1] * 0.25, (b * 0.999).cos()]
+            } else {
+                [(a * 0.999 + x).sin() + y, (b * 0.999 + y).cos()]
+            };
+        }
+        let z = tape.create_variable(2.0);
+        let [a, b] = books;
+        let c = a * z;
+
+        for (output, inputs) in [(a, [x, y, z]), (b, [y, x, z]), (c, [z, x, y])] {
+            let expected = output
+                .compute_gradients()
+                .unwrap()
+                .get_gradients(&inputs)
+                .unwrap();
+            assert_eq!(
+                tape.pruned_gradients(&output, &inputs),
+                Ok(expected.to_vec())
+            );
+        }
+        let outputs = external(&tape, x, y);
+        let expected = outputs[0]
+            .compute_gradients()
+            .unwrap()
+            .get_gradients(&[x, y])
+            .unwrap();
+        assert_eq!(
+            tape.pruned_gradients(&outputs[0], &[x, y]),
+            Ok(expected.to_vec())
+        );
+        assert_eq!(tape.pruned_gradients(&c, &[]), Ok(vec![]));
+        assert_eq!(tape.pruned_gradients(&x, &[x]), Ok(vec![1.0]));
+        assert_eq!(
+            tape.pruned_gradients(&c, &[Variable::constant(1.0)]),
+            Err(GradientError::MissingIndex)
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp, clippy::many_single_char_names)]
+    fn test_preaccumulate_matches_taped() {
+        fn kernel<'a>(
+            tape: &'a Tape<f64>,
+            x: Variable<'a, f64>,
+            y: Variable<'a, f64>,
+        ) -> [Variable<'a, f64>; 2] {
+            let mut a = x;
+            for _ in 0..50 {
+                a = (a * y).sin() + x;
+            }
+            let outputs = external(tape, a, y);
+            [outputs[0] * 0.5 + a, outputs[1].ln()]
+        }
+
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[0.5, 1.5]);
+        let [a, b] = kernel(&tape, x, y);
+        let z = a * b + x;
+        let expected = z
+            .compute_gradients()
+            .unwrap()
+            .get_gradients(&[x, y])
+            .unwrap();
+
+        tape.clear();
+        let [x, y] = tape.create_variables(&[0.5, 1.5]);
+        let w = x * 2.0;
+        let mark = tape.mark();
+        let [a, b] = kernel(&tape, x, y);
+        let collapsed = tape.preaccumulate(mark, &[a, b, w]).unwrap();
+        assert_eq!(tape.len(), 5);
+        assert_eq!(collapsed[2].index(), w.index());
+        assert_eq!(
+            a.compute_gradients().err(),
+            Some(GradientError::StaleVariable(a.index.unwrap().0))
+        );
+        let z = collapsed[0] * collapsed[1] + x;
+        assert_eq!(z.value(), a.value() * b.value() + x.value());
+        let grads = z
+            .compute_gradients()
+            .unwrap()
+            .get_gradients(&[x, y])
+            .unwrap();
+        for (grad, expected) in grads.iter().zip(expected) {
+            assert!((grad - expected).abs() < 1e-12);
+        }
+        let mark = tape.mark();
+        let outputs = external(&tape, x, y);
+        let collapsed = tape.preaccumulate(mark, &[outputs[0]]).unwrap();
+        assert_eq!(
+            tape.node(collapsed[0].index().unwrap()).unwrap().parents,
+            [(0, 1.5), (1, 0.5)]
+        );
+        assert_eq!(
+            tape.preaccumulate(tape.mark(), &[Variable::constant(1.0)]),
+            Err(GradientError::MissingIndex)
+        );
+    }
+
+    #[test]
+    fn test_preaccumulate_rejects_segment_variables() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0);
+        let mark = tape.mark();
+        let y = tape.create_variable(3.0);
+        let z = x * y;
+        assert_eq!(
+            tape.preaccumulate(mark, &[z]).err(),
+            Some(GradientError::SegmentVariable(1))
+        );
+        assert_eq!(tape.len(), 3);
+        assert_eq!(
+            z.compute_gradients().unwrap().get_gradients(&[x, y]),
+            Ok([3.0, 2.0])
+        );
+    }
+
+    #[test]
+    fn test_drain_gradients_empties_tape() {
+        let tape = Tape::with_capacity(16);
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let z = x * y;
+        assert_eq!(tape.drain_gradients(&z, &[x, y]), Ok(vec![3.0, 2.0]));
+        assert!(tape.is_empty());
+        assert_eq!(tape.operations.borrow().capacity(), 0);
+        assert_eq!(
+            z.compute_gradients().err(),
+            Some(GradientError::StaleVariable(2))
+        );
+
+        let x = tape.create_variable(4.0);
+        let z = x * x;
+        assert_eq!(tape.drain_gradients(&z, &[x, x]), Ok(vec![8.0, 8.0]));
+    }
+
+    #[test]
+    fn test_drain_gradients_rejects_invalid_variables() {
+        let tape = Tape::new();
+        let other = Tape::new();
+        let x = tape.create_variable(2.0);
+        let z = x * x;
+        let y = other.create_variable(1.0);
+        assert_eq!(
+            tape.drain_gradients(&z, &[x, y]),
+            Err(GradientError::TapeMismatch)
+        );
+        assert_eq!(
+            tape.drain_gradients(&Variable::constant(1.0), &[x]),
+            Err(GradientError::MissingIndex)
+        );
+        assert_eq!(tape.len(), 2);
+        assert_eq!(tape.drain_gradients(&z, &[x]), Ok(vec![4.0]));
+    }
+
+    #[test]
+    fn test_drain_gradients_with_inputs_recorded_after_output() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0_f64);
+        let z = x.sin();
+        let mut w = z;
+        for _ in 0..5000 {
+            w = w * 0.5 + x;
+        }
+        let y = tape.create_variable(3.0);
+        assert_eq!(
+            tape.drain_gradients(&z, &[y, x]),
+            Ok(vec![0.0, 2.0_f64.cos()])
+        );
+
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let outputs = external(&tape, x, y);
+        let expected = outputs[0]
+            .compute_gradients()
+            .unwrap()
+            .get_gradients(&[x, y])
+            .unwrap();
+        assert_eq!(
+            tape.drain_gradients(&outputs[0], &[x, y]),
+            Ok(expected.to_vec())
+        );
+    }
+
+    #[test]
+    fn test_drain_gradients_with_external_straddling_step() {
+        fn record(tape: &Tape<f64>) -> [Variable<'_, f64>; 2] {
+            let x = tape.create_variable(1.0);
+            let mut z = x;
+            for _ in 0..100 {
+                z *= 1.0;
+            }
+            // The outputs span several adjoint blocks and the sum crosses a drain step.
+            let outputs = tape.record_external(&[z], &vec![1.0; 40000], |w| vec![w.iter().sum()]);
+            let s = outputs.iter().fold(Variable::constant(0.0), |s, &o| s + o);
+            [x, s]
+        }
+
+        let tape = Tape::new();
+        let [x, s] = record(&tape);
+        assert_eq!(s.compute_gradients().unwrap().get_gradient(&x), Ok(40000.0));
+        tape.clear();
+        let [x, s] = record(&tape);
+        assert!(tape.len() > super::DRAIN_STEP);
+        assert_eq!(tape.drain_gradients(&s, &[x]), Ok(vec![40000.0]));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_rewind_to_mark() {
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let mark = tape.mark();
+
+        for _ in 0..3 {
+            let z = x * y + x;
+            let grads = z.compute_gradients().unwrap();
+            assert_eq!(grads.get_gradients(&[x, y]).unwrap(), [4.0, 2.0]);
+            tape.rewind_to(mark);
+            assert_eq!(tape.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_rewind_keeps_allocation() {
+        let tape = Tape::with_capacity(16);
+        let x = tape.create_variable(1.0);
+        let mark = tape.mark();
+        let _ = x * x * x;
+        let capacity = tape.operations.borrow().capacity();
+        tape.rewind_to(mark);
+        assert_eq!(tape.operations.borrow().capacity(), capacity);
+        tape.clear();
+        assert!(tape.is_empty());
+        assert_eq!(tape.operations.borrow().capacity(), capacity);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_stale_variable() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0);
+        let mark = tape.mark();
+        let stale = x * x;
+        tape.rewind_to(mark);
+
+        assert_eq!(
+            stale.compute_gradients().err(),
+            Some(GradientError::StaleVariable(1))
+        );
+
+        // The slot of `stale` is reused by a new node, which must not alias it.
+        let z = x * x * x;
+        let grads = z.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradient(&x).unwrap(), 12.0);
+        assert_eq!(
+            grads.get_gradient(&stale),
+            Err(GradientError::StaleVariable(1))
+        );
+        assert_eq!(
+            stale.compute_gradients().err(),
+            Some(GradientError::StaleVariable(1))
+        );
+    }
+
+    #[test]
+    fn test_clear_invalidates_all_variables() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0);
+        tape.clear();
+        let y = tape.create_variable(3.0);
+        assert_eq!(
+            x.compute_gradients().err(),
+            Some(GradientError::StaleVariable(0))
+        );
+        assert_eq!(y.compute_gradients().unwrap().get_gradient(&y), Ok(1.0));
+    }
+
+    #[test]
+    fn test_nested_marks() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0);
+        let outer = tape.mark();
+        let y = x * x;
+        let inner = tape.mark();
+        for _ in 0..3 {
+            let z = y * x;
+            assert_eq!(z.compute_gradients().unwrap().get_gradient(&x), Ok(12.0));
+            tape.rewind_to(inner);
+        }
+        assert!(y.compute_gradients().is_ok());
+        tape.rewind_to(outer);
+        assert_eq!(
+            y.compute_gradients().err(),
+            Some(GradientError::StaleVariable(1))
+        );
+        assert!(x.compute_gradients().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "tape mark has been invalidated")]
+    fn test_rewind_to_invalidated_mark() {
+        let tape = Tape::new();
+        let x = tape.create_variable(2.0);
+        let outer = tape.mark();
+        let _ = x * x;
+        let inner = tape.mark();
+        tape.rewind_to(outer);
+        tape.rewind_to(inner);
+    }
 }
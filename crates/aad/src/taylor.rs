@@ -0,0 +1,690 @@
+use crate::FloatLike;
+use crate::dual::impl_forward_op;
+use num_traits::{One, Zero};
+use std::cmp::Ordering;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Clone, Copy, Debug)]
+/// A truncated univariate Taylor polynomial `x(t) = x_0 + x_1 t + ... + x_K t^K`.
+///
+/// Evaluating a function generic over [`FloatLike`] with a `Taylor` argument propagates all
+/// Taylor coefficients up to order `K` in one pass, so the `k`-th derivative along the seeded
+/// direction is `k! * x_k`.
+///
+/// # Type Parameters
+///
+/// * `F` - The underlying numeric type (typically `f64`)
+/// * `K` - The highest propagated order
+///
+/// # Examples
+///
+/// ```
+/// use aad::Taylor;
+///
+/// let x = Taylor::<f64, 3>::variable(2.0);
+/// let y = x * x * x;
+/// assert_eq!(y.value(), 8.0);
+/// assert_eq!(y.derivatives(), [12.0, 12.0, 6.0]);
+/// ```
+pub struct Taylor<F, const K: usize> {
+    value: F,
+    coefficients: [F; K],
+}
+
+#[allow(clippy::cast_precision_loss)]
+#[inline]
+fn order(k: usize) -> f64 {
+    k as f64
+}
+
+impl<F: Copy, const K: usize> Taylor<F, K> {
+    #[inline]
+    #[must_use]
+    /// Creates a polynomial from its value and its coefficients of order `1..=K`.
+    pub const fn new(value: F, coefficients: [F; K]) -> Self {
+        Self {
+            value,
+            coefficients,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn value(&self) -> F {
+        self.value
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the coefficients of order `1..=K`.
+    pub const fn coefficients(&self) -> [F; K] {
+        self.coefficients
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the coefficient of order `k`, where order `0` is the value.
+    pub const fn coefficient(&self, k: usize) -> F {
+        if k == 0 {
+            self.value
+        } else {
+            self.coefficients[k - 1]
+        }
+    }
+
+    #[inline]
+    fn set(&mut self, k: usize, coefficient: F) {
+        if k == 0 {
+            self.value = coefficient;
+        } else {
+            self.coefficients[k - 1] = coefficient;
+        }
+    }
+}
+
+impl<F: Copy + Zero + One, const K: usize> Taylor<F, K> {
+    #[inline]
+    #[must_use]
+    /// Creates a polynomial with all coefficients of positive order set to zero.
+    pub fn constant(value: F) -> Self {
+        Self::new(value, [F::zero(); K])
+    }
+
+    #[inline]
+    #[must_use]
+    /// Creates the independent variable `x(t) = value + t`.
+    pub fn variable(value: F) -> Self {
+        let mut coefficients = [F::zero(); K];
+        if let Some(first) = coefficients.first_mut() {
+            *first = F::one();
+        }
+        Self::new(value, coefficients)
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Taylor<F, K> {
+    #[inline]
+    #[must_use]
+    /// Returns the derivatives of order `1..=K`, i.e. `k! * x_k`.
+    pub fn derivatives(&self) -> [F; K] {
+        let mut factorial = 1.0;
+        std::array::from_fn(|i| {
+            factorial *= order(i + 1);
+            self.coefficients[i] * factorial
+        })
+    }
+
+    /// Returns the Taylor expansion of `f(self)` given `f(x_0)` and the expansion of `f'(self)`.
+    ///
+    /// Uses `k r_k = sum_{j=1}^{k} j x_j d_{k-j}`, which follows from `r' = f'(x) x'`.
+    fn integrate(self, value: F, derivative: Self) -> Self {
+        let mut result = Self::constant(value);
+        for k in 1..=K {
+            let mut sum = F::zero();
+            for j in 1..=k {
+                sum += self.coefficient(j) * derivative.coefficient(k - j) * order(j);
+            }
+            result.set(k, sum / order(k));
+        }
+        result
+    }
+
+    /// Returns the expansion of `self^exponent` given its value `x_0^exponent`.
+    ///
+    /// Uses `k x_0 p_k = sum_{j=0}^{k-1} (exponent (k - j) - j) x_{k-j} p_j`, which follows from
+    /// `x p' = exponent p x'`.
+    fn power(self, value: F, exponent: f64) -> Self {
+        let mut result = Self::constant(value);
+        for k in 1..=K {
+            let mut sum = F::zero();
+            for j in 0..k {
+                sum += self.coefficient(k - j)
+                    * result.coefficient(j)
+                    * (exponent * order(k - j) - order(j));
+            }
+            result.set(k, sum / (self.value * order(k)));
+        }
+        result
+    }
+
+    /// Returns the expansions of `sin(self)` and `cos(self)`, which depend on each other.
+    fn sin_cos(self) -> (Self, Self) {
+        let mut sin = Self::constant(self.value.sin());
+        let mut cos = Self::constant(self.value.cos());
+        for k in 1..=K {
+            let (mut s, mut c) = (F::zero(), F::zero());
+            for j in 1..=k {
+                let a = self.coefficient(j) * order(j);
+                s += a * cos.coefficient(k - j);
+                c -= a * sin.coefficient(k - j);
+            }
+            sin.set(k, s / order(k));
+            cos.set(k, c / order(k));
+        }
+        (sin, cos)
+    }
+
+    /// Returns the expansions of `sinh(self)` and `cosh(self)`, which depend on each other.
+    fn sinh_cosh(self) -> (Self, Self) {
+        let mut sinh = Self::constant(self.value.sinh());
+        let mut cosh = Self::constant(self.value.cosh());
+        for k in 1..=K {
+            let (mut s, mut c) = (F::zero(), F::zero());
+            for j in 1..=k {
+                let a = self.coefficient(j) * order(j);
+                s += a * cosh.coefficient(k - j);
+                c += a * sinh.coefficient(k - j);
+            }
+            sinh.set(k, s / order(k));
+            cosh.set(k, c / order(k));
+        }
+        (sinh, cosh)
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Neg for Taylor<F, K> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Self::new(-self.value, self.coefficients.map(Neg::neg))
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Add for Taylor<F, K> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.value + rhs.value,
+            std::array::from_fn(|i| self.coefficients[i] + rhs.coefficients[i]),
+        )
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Sub for Taylor<F, K> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.value - rhs.value,
+            std::array::from_fn(|i| self.coefficients[i] - rhs.coefficients[i]),
+        )
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl<F: FloatLike<f64>, const K: usize> Mul for Taylor<F, K> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut result = Self::constant(self.value * rhs.value);
+        for k in 1..=K {
+            let mut sum = F::zero();
+            for j in 0..=k {
+                sum += self.coefficient(j) * rhs.coefficient(k - j);
+            }
+            result.set(k, sum);
+        }
+        result
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl<F: FloatLike<f64>, const K: usize> Div for Taylor<F, K> {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        let mut result = Self::constant(self.value / rhs.value);
+        for k in 1..=K {
+            let mut sum = self.coefficient(k);
+            for j in 0..k {
+                sum -= result.coefficient(j) * rhs.coefficient(k - j);
+            }
+            result.set(k, sum / rhs.value);
+        }
+        result
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Add<f64> for Taylor<F, K> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: f64) -> Self::Output {
+        Self::new(self.value + rhs, self.coefficients)
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Sub<f64> for Taylor<F, K> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: f64) -> Self::Output {
+        Self::new(self.value - rhs, self.coefficients)
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Mul<f64> for Taylor<F, K> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self::new(self.value * rhs, self.coefficients.map(|c| c * rhs))
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Div<f64> for Taylor<F, K> {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: f64) -> Self::Output {
+        Self::new(self.value / rhs, self.coefficients.map(|c| c / rhs))
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Add<Taylor<F, K>> for f64 {
+    type Output = Taylor<F, K>;
+
+    #[inline]
+    fn add(self, rhs: Taylor<F, K>) -> Self::Output {
+        rhs + self
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Sub<Taylor<F, K>> for f64 {
+    type Output = Taylor<F, K>;
+
+    #[inline]
+    fn sub(self, rhs: Taylor<F, K>) -> Self::Output {
+        -rhs + self
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Mul<Taylor<F, K>> for f64 {
+    type Output = Taylor<F, K>;
+
+    #[inline]
+    fn mul(self, rhs: Taylor<F, K>) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Div<Taylor<F, K>> for f64 {
+    type Output = Taylor<F, K>;
+
+    #[inline]
+    fn div(self, rhs: Taylor<F, K>) -> Self::Output {
+        Taylor::constant(F::from(self)) / rhs
+    }
+}
+
+impl_forward_op!(Taylor, Add, add, AddAssign, add_assign);
+impl_forward_op!(Taylor, Sub, sub, SubAssign, sub_assign);
+impl_forward_op!(Taylor, Mul, mul, MulAssign, mul_assign);
+impl_forward_op!(Taylor, Div, div, DivAssign, div_assign);
+
+impl<F: FloatLike<f64>, const K: usize> Zero for Taylor<F, K> {
+    #[inline]
+    fn zero() -> Self {
+        Self::constant(F::zero())
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.value.is_zero() && self.coefficients.iter().all(Zero::is_zero)
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> One for Taylor<F, K> {
+    #[inline]
+    fn one() -> Self {
+        Self::constant(F::one())
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> Sum for Taylor<F, K> {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, x| acc + x)
+    }
+}
+
+impl<'a, F: FloatLike<f64>, const K: usize> Sum<&'a Self> for Taylor<F, K> {
+    #[inline]
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, x| acc + *x)
+    }
+}
+
+impl<F: PartialEq, const K: usize> PartialEq for Taylor<F, K> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<F: PartialOrd, const K: usize> PartialOrd for Taylor<F, K> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<F: PartialEq<f64>, const K: usize> PartialEq<f64> for Taylor<F, K> {
+    #[inline]
+    fn eq(&self, other: &f64) -> bool {
+        self.value == *other
+    }
+}
+
+impl<F: PartialOrd<f64>, const K: usize> PartialOrd<f64> for Taylor<F, K> {
+    #[inline]
+    fn partial_cmp(&self, other: &f64) -> Option<Ordering> {
+        self.value.partial_cmp(other)
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> From<f64> for Taylor<F, K> {
+    #[inline]
+    fn from(value: f64) -> Self {
+        Self::constant(F::from(value))
+    }
+}
+
+impl<F: Into<f64>, const K: usize> From<Taylor<F, K>> for f64 {
+    #[inline]
+    fn from(value: Taylor<F, K>) -> Self {
+        value.value.into()
+    }
+}
+
+impl<F: FloatLike<f64>, const K: usize> FloatLike<f64> for Taylor<F, K> {
+    #[inline]
+    fn sin(self) -> Self {
+        self.sin_cos().0
+    }
+
+    #[inline]
+    fn cos(self) -> Self {
+        self.sin_cos().1
+    }
+
+    #[inline]
+    fn tan(self) -> Self {
+        let (sin, cos) = self.sin_cos();
+        sin / cos
+    }
+
+    #[inline]
+    fn sinh(self) -> Self {
+        self.sinh_cosh().0
+    }
+
+    #[inline]
+    fn cosh(self) -> Self {
+        self.sinh_cosh().1
+    }
+
+    #[inline]
+    fn tanh(self) -> Self {
+        let (sinh, cosh) = self.sinh_cosh();
+        sinh / cosh
+    }
+
+    #[inline]
+    fn ln(self) -> Self {
+        self.integrate(self.value.ln(), self.recip())
+    }
+
+    #[inline]
+    fn log(self, base: f64) -> Self {
+        self.ln() / base.ln()
+    }
+
+    #[inline]
+    fn log2(self) -> Self {
+        self.ln() / std::f64::consts::LN_2
+    }
+
+    #[inline]
+    fn log10(self) -> Self {
+        self.ln() / std::f64::consts::LN_10
+    }
+
+    #[inline]
+    fn exp(self) -> Self {
+        let mut result = Self::constant(self.value.exp());
+        for k in 1..=K {
+            let mut sum = F::zero();
+            for j in 1..=k {
+                sum += self.coefficient(j) * result.coefficient(k - j) * order(j);
+            }
+            result.set(k, sum / order(k));
+        }
+        result
+    }
+
+    #[inline]
+    fn exp2(self) -> Self {
+        (self * std::f64::consts::LN_2).exp()
+    }
+
+    #[inline]
+    fn powf(self, exponent: f64) -> Self {
+        self.power(self.value.powf(exponent), exponent)
+    }
+
+    #[inline]
+    fn powi(self, exponent: i32) -> Self {
+        let mut base = self;
+        let mut result = Self::one();
+        let mut n = exponent.unsigned_abs();
+        while n > 0 {
+            if n & 1 == 1 {
+                result *= base;
+            }
+            base *= base;
+            n >>= 1;
+        }
+        if exponent < 0 { result.recip() } else { result }
+    }
+
+    #[inline]
+    fn sqrt(self) -> Self {
+        let mut result = Self::constant(self.value.sqrt());
+        for k in 1..=K {
+            let mut sum = self.coefficient(k);
+            for j in 1..k {
+                sum -= result.coefficient(j) * result.coefficient(k - j);
+            }
+            result.set(k, sum / (result.value * 2.0));
+        }
+        result
+    }
+
+    #[inline]
+    fn cbrt(self) -> Self {
+        self.power(self.value.cbrt(), 1.0 / 3.0)
+    }
+
+    #[inline]
+    fn recip(self) -> Self {
+        Self::one() / self
+    }
+
+    #[inline]
+    fn abs(self) -> Self {
+        if self.value < 0.0 { -self } else { self }
+    }
+
+    #[inline]
+    fn asin(self) -> Self {
+        self.integrate(
+            self.value.asin(),
+            (Self::one() - self * self).sqrt().recip(),
+        )
+    }
+
+    #[inline]
+    fn acos(self) -> Self {
+        self.integrate(
+            self.value.acos(),
+            -(Self::one() - self * self).sqrt().recip(),
+        )
+    }
+
+    #[inline]
+    fn atan(self) -> Self {
+        self.integrate(self.value.atan(), (Self::one() + self * self).recip())
+    }
+
+    #[inline]
+    fn asinh(self) -> Self {
+        self.integrate(
+            self.value.asinh(),
+            (self * self + Self::one()).sqrt().recip(),
+        )
+    }
+
+    #[inline]
+    fn acosh(self) -> Self {
+        self.integrate(
+            self.value.acosh(),
+            (self * self - Self::one()).sqrt().recip(),
+        )
+    }
+
+    #[inline]
+    fn atanh(self) -> Self {
+        self.integrate(self.value.atanh(), (Self::one() - self * self).recip())
+    }
+
+    #[inline]
+    fn hypot(self, other: Self) -> Self {
+        let mut result = (self * self + other * other).sqrt();
+        result.value = self.value.hypot(other.value);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-10;
+
+    fn assert_close<const K: usize>(actual: [f64; K], expected: [f64; K]) {
+        for (a, e) in actual.iter().zip(&expected) {
+            assert!(
+                (a - e).abs() < EPSILON * e.abs().max(1.0),
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_polynomial() {
+        let x = Taylor::<f64, 4>::variable(2.0);
+        let y = 3.0 * x.powi(3) - x * x + 1.0 / x;
+        assert_eq!(y.value(), 24.0 - 4.0 + 0.5);
+        assert_close(
+            y.derivatives(),
+            [36.0 - 4.0 - 0.25, 36.0 - 2.0 + 0.25, 18.0 - 0.375, 0.75],
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp, clippy::many_single_char_names)]
+    fn test_elementary_functions() {
+        let a = 0.3_f64;
+        let x = Taylor::<f64, 4>::variable(a);
+
+        let (s, c) = (a.sin(), a.cos());
+        assert_close(x.sin().derivatives(), [c, -s, -c, s]);
+        assert_close(x.cos().derivatives(), [-s, -c, s, c]);
+        let e = a.exp();
+        assert_close(x.exp().derivatives(), [e; 4]);
+        assert_close(
+            x.ln().derivatives(),
+            [1.0 / a, -1.0 / a.powi(2), 2.0 / a.powi(3), -6.0 / a.powi(4)],
+        );
+        let p = 2.5;
+        assert_close(
+            x.powf(p).derivatives(),
+            [
+                p * a.powf(p - 1.0),
+                p * (p - 1.0) * a.powf(p - 2.0),
+                p * (p - 1.0) * (p - 2.0) * a.powf(p - 3.0),
+                p * (p - 1.0) * (p - 2.0) * (p - 3.0) * a.powf(p - 4.0),
+            ],
+        );
+        assert_close(x.sqrt().derivatives(), x.powf(0.5).derivatives());
+        assert_close(x.cbrt().derivatives(), x.powf(1.0 / 3.0).derivatives());
+        let y = Taylor::<f64, 2>::variable(-8.0).cbrt();
+        assert_eq!(y.value(), -2.0);
+        assert_close(y.derivatives(), [1.0 / 12.0, 1.0 / 144.0]);
+        let (sh, ch) = (a.sinh(), a.cosh());
+        assert_close(x.sinh().derivatives(), [ch, sh, ch, sh]);
+        assert_close(x.cosh().derivatives(), [sh, ch, sh, ch]);
+    }
+
+    #[test]
+    fn test_inverse_functions() {
+        let a = 0.3_f64;
+        let x = Taylor::<f64, 3>::variable(a);
+        let d = 1.0 - a * a;
+        assert_close(
+            x.atanh().derivatives(),
+            [
+                1.0 / d,
+                2.0 * a / d.powi(2),
+                (2.0 + 6.0 * a * a) / d.powi(3),
+            ],
+        );
+        assert_close(
+            x.asin().derivatives(),
+            [
+                d.powf(-0.5),
+                a * d.powf(-1.5),
+                d.powf(-1.5) + 3.0 * a * a * d.powf(-2.5),
+            ],
+        );
+        let acos = x.acos().derivatives();
+        let asin = x.asin().derivatives();
+        assert_close(acos, asin.map(|v| -v));
+        let e = 1.0 + a * a;
+        assert_close(
+            x.atan().derivatives(),
+            [
+                1.0 / e,
+                -2.0 * a / e.powi(2),
+                (6.0 * a * a - 2.0) / e.powi(3),
+            ],
+        );
+        assert_close(x.tan().derivatives(), (x.sin() / x.cos()).derivatives());
+    }
+
+    #[test]
+    fn test_matches_dual_first_order() {
+        use crate::Dual;
+
+        fn f<T: FloatLike<f64>>(x: T) -> T {
+            x.tanh() * x.exp2() + x.log(3.0) - x.hypot(x * 2.0)
+                + (x * x + 1.0).acosh()
+                + x.asinh()
+                + x.log10() * x.log2()
+        }
+
+        let taylor = f(Taylor::<f64, 1>::variable(0.8));
+        let dual = f(Dual::<f64, 1>::variable(0.8, 0));
+        assert!((taylor.value() - dual.value()).abs() < EPSILON);
+        assert_close(taylor.derivatives(), dual.tangents());
+    }
+}
@@ -1,8 +1,11 @@
+use std::any::TypeId;
 use std::cmp::Ordering;
-use std::ops::{Add, Mul};
+use std::marker::PhantomData;
+use std::ops::{Add, Div, Mul, Sub};
 
-use crate::gradients::{GradientError, Gradients};
+use crate::gradients::{GradientBuffer, GradientError, Gradients};
 use crate::operation_record::OperationRecord;
+use crate::replay::{BranchTest, OpKind, Operand};
 use crate::tape::Tape;
 use num_traits::{One, Zero};
 
@@ -14,7 +17,8 @@ use num_traits::{One, Zero};
 ///
 /// # Type Parameters
 ///
-/// * `'a` - The lifetime of the reference to the tape
+/// * `'a` - The lifetime of the reference to the tape. It is invariant, so that variables
+///   created through a [`BrandedTape`](crate::tape::BrandedTape) cannot be mixed across scopes
 /// * `F` - The underlying numeric type (typically `f32` or `f64`)
 ///
 /// # Fields
@@ -24,107 +28,253 @@ use num_traits::{One, Zero};
 /// * `value` - The current value of the variable
 pub struct Variable<'a, F> {
     pub(crate) index: Option<(usize, &'a Tape<F>)>,
+    pub(crate) generation: usize,
     pub(crate) value: F,
+    pub(crate) brand: PhantomData<fn(&'a ()) -> &'a ()>,
 }
 
-type BinaryFn<T, S = T> = fn(T, S) -> T;
-type UnaryFn<T> = fn(T) -> T;
-type BinaryPairFn<T> = fn(T, T) -> (T, T);
-
 impl<F: Copy> Variable<'_, F> {
     #[inline]
     #[must_use]
     pub const fn value(&self) -> F {
         self.value
     }
+
     #[inline]
     #[must_use]
-    pub fn apply_binary_function(&self, rhs: &Self, f: BinaryFn<F>, dfdx: BinaryPairFn<F>) -> Self {
-        #[inline]
-        fn create_index<'a, F>(
-            value: F,
-            rhs: Variable<'a, F>,
-            dfdx: fn(F, F) -> (F, F),
-            idx: [usize; 2],
-            tape: &'a Tape<F>,
-        ) -> usize {
-            let operations = &mut tape.operations.borrow_mut();
-            let count = (*operations).len();
-            let df = dfdx(value, rhs.value);
-            (*operations).push(OperationRecord([(idx[0], df.0), (idx[1], df.1)]));
-            count
-        }
+    /// Returns the index of the variable's node on its tape, or `None` for a constant.
+    pub fn index(&self) -> Option<usize> {
+        self.index.map(|(index, _)| index)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Records a binary operation given its value function `f` and its partial derivatives `dfdx`.
+    ///
+    /// Both may be closures, so derivatives depending on captured state can be expressed.
+    pub fn apply_binary_function(
+        &self,
+        rhs: &Self,
+        f: impl FnOnce(F, F) -> F,
+        dfdx: impl FnOnce(F, F) -> (F, F),
+    ) -> Self {
+        self.binary_op(rhs, OpKind::Opaque, f, dfdx)
+    }
+
+    #[inline]
+    /// Records a binary operation of the given `kind`.
+    pub(crate) fn binary_op(
+        &self,
+        rhs: &Self,
+        kind: OpKind,
+        f: impl FnOnce(F, F) -> F,
+        dfdx: impl FnOnce(F, F) -> (F, F),
+    ) -> Self {
         let value = f(self.value, rhs.value);
+        match (self.live_index(), rhs.live_index()) {
+            (Some((i, tape)), Some((j, other))) => {
+                tape.debug_assert_same(other);
+                let df = dfdx(self.value, rhs.value);
+                tape.record(value, OperationRecord([(i, df.0), (j, df.1)]), kind, None)
+            }
+            (None, None) => Variable::constant(value),
+            (None, Some((j, tape))) => {
+                let df = dfdx(self.value, rhs.value);
+                tape.record(
+                    value,
+                    OperationRecord([(usize::MAX, df.0), (j, df.1)]),
+                    kind,
+                    Some((0, self.value)),
+                )
+            }
+            (Some((i, tape)), None) => {
+                let df = dfdx(self.value, rhs.value);
+                tape.record(
+                    value,
+                    OperationRecord([(i, df.0), (usize::MAX, df.1)]),
+                    kind,
+                    Some((1, rhs.value)),
+                )
+            }
+        }
+    }
+}
+
+impl<'a, F> Variable<'a, F> {
+    #[inline]
+    /// Returns the node and tape of `self`, for recording an operation on it.
+    ///
+    /// If `self` was invalidated by rewinding its tape, its node may have been reused by
+    /// another variable since, so the recorded result is stale as well and sweeps from it
+    /// return `GradientError::StaleVariable`.
+    pub(crate) fn live_index(&self) -> Option<(usize, &'a Tape<F>)> {
+        if let Some((index, tape)) = self.index {
+            tape.read_operand(index, self.generation);
+        }
+        self.index
+    }
+
+    #[inline]
+    /// Checks that `self` and `rhs` can be combined into a single operation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GradientError::TapeMismatch` if both variables are recorded on different tapes
+    pub fn check_same_tape(&self, rhs: &Self) -> Result<(), GradientError> {
         match (self.index, rhs.index) {
-            (Some((i, tape)), Some((j, _))) => Variable {
-                index: Some((create_index(self.value, *rhs, dfdx, [i, j], tape), tape)),
-                value,
-            },
-            (None, None) => Variable { index: None, value },
-            (None, Some((j, tape))) => Variable {
-                index: Some((
-                    create_index(self.value, *rhs, dfdx, [usize::MAX, j], tape),
-                    tape,
-                )),
-                value,
-            },
-            (Some((i, tape)), None) => Variable {
-                index: Some((
-                    create_index(self.value, *rhs, dfdx, [i, usize::MAX], tape),
-                    tape,
-                )),
-                value,
-            },
+            (Some((_, tape)), Some((_, other))) if !std::ptr::eq(tape, other) => {
+                Err(GradientError::TapeMismatch)
+            }
+            _ => Ok(()),
         }
     }
+
+    #[inline]
+    /// Adds `rhs` to `self`, failing instead of panicking if they belong to different tapes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GradientError::TapeMismatch` if both variables are recorded on different tapes
+    pub fn try_add(&self, rhs: &Self) -> Result<Self, GradientError>
+    where
+        for<'b> &'b Self: Add<&'b Self, Output = Self>,
+    {
+        self.check_same_tape(rhs)?;
+        Ok(self + rhs)
+    }
+
+    #[inline]
+    /// Subtracts `rhs` from `self`, failing instead of panicking if they belong to different tapes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GradientError::TapeMismatch` if both variables are recorded on different tapes
+    pub fn try_sub(&self, rhs: &Self) -> Result<Self, GradientError>
+    where
+        for<'b> &'b Self: Sub<&'b Self, Output = Self>,
+    {
+        self.check_same_tape(rhs)?;
+        Ok(self - rhs)
+    }
+
+    #[inline]
+    /// Multiplies `self` by `rhs`, failing instead of panicking if they belong to different tapes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GradientError::TapeMismatch` if both variables are recorded on different tapes
+    pub fn try_mul(&self, rhs: &Self) -> Result<Self, GradientError>
+    where
+        for<'b> &'b Self: Mul<&'b Self, Output = Self>,
+    {
+        self.check_same_tape(rhs)?;
+        Ok(self * rhs)
+    }
+
+    #[inline]
+    /// Divides `self` by `rhs`, failing instead of panicking if they belong to different tapes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GradientError::TapeMismatch` if both variables are recorded on different tapes
+    pub fn try_div(&self, rhs: &Self) -> Result<Self, GradientError>
+    where
+        for<'b> &'b Self: Div<&'b Self, Output = Self>,
+    {
+        self.check_same_tape(rhs)?;
+        Ok(self / rhs)
+    }
+}
+
+impl<F: Copy> Variable<'_, F> {
+    #[inline]
+    /// Checked counterpart of [`Variable::apply_binary_function`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `GradientError::TapeMismatch` if both variables are recorded on different tapes
+    pub fn try_apply_binary_function(
+        &self,
+        rhs: &Self,
+        f: impl FnOnce(F, F) -> F,
+        dfdx: impl FnOnce(F, F) -> (F, F),
+    ) -> Result<Self, GradientError> {
+        self.check_same_tape(rhs)?;
+        Ok(self.apply_binary_function(rhs, f, dfdx))
+    }
 }
 
 impl<F: Copy + Zero> Variable<'_, F> {
     #[inline]
     #[must_use]
-    pub fn apply_unary_function(&self, f: UnaryFn<F>, df: UnaryFn<F>) -> Self {
+    /// Records a unary operation given its value function `f` and its derivative `df`.
+    ///
+    /// Both may be closures, so derivatives depending on captured state can be expressed.
+    pub fn apply_unary_function(&self, f: impl FnOnce(F) -> F, df: impl FnOnce(F) -> F) -> Self {
+        self.unary_op(OpKind::Opaque, f, df)
+    }
+
+    #[inline]
+    /// Records a unary operation of the given `kind`.
+    pub(crate) fn unary_op(
+        &self,
+        kind: OpKind,
+        f: impl FnOnce(F) -> F,
+        df: impl FnOnce(F) -> F,
+    ) -> Self {
         let value = f(self.value);
-        match self.index {
-            Some((i, tape)) => Variable {
-                index: {
-                    let operations = &mut tape.operations.borrow_mut();
-                    let count = (*operations).len();
-                    (*operations).push(OperationRecord([
-                        (i, df(self.value)),
-                        (usize::MAX, F::zero()),
-                    ]));
-                    Some((count, tape))
-                },
+        match self.live_index() {
+            Some((i, tape)) => tape.record(
                 value,
-            },
-            None => Variable { index: None, value },
+                OperationRecord([(i, df(self.value)), (usize::MAX, F::zero())]),
+                kind,
+                None,
+            ),
+            None => Variable::constant(value),
         }
     }
 
     #[inline]
     #[must_use]
+    /// Records a unary operation parameterized by `scalar`, given its value function `f` and
+    /// its derivative `df` with respect to the variable.
     pub fn apply_scalar_function<T: Copy>(
         &self,
-        f: BinaryFn<F, T>,
-        df: BinaryFn<F, T>,
+        f: impl FnOnce(F, T) -> F,
+        df: impl FnOnce(F, T) -> F,
         scalar: T,
     ) -> Self {
-        let value = f(self.value, scalar);
-        match self.index {
-            Some((i, tape)) => Variable {
-                index: {
-                    let operations = &mut tape.operations.borrow_mut();
-                    let count = (*operations).len();
-                    (*operations).push(OperationRecord([
-                        (i, df(self.value, scalar)),
-                        (usize::MAX, F::zero()),
-                    ]));
-                    Some((count, tape))
-                },
-                value,
-            },
-            None => Variable { index: None, value },
-        }
+        self.unary_op(OpKind::Opaque, |x| f(x, scalar), |x| df(x, scalar))
+    }
+}
+
+impl<F: Copy + Zero> Variable<'_, F> {
+    #[inline]
+    #[must_use]
+    /// Records a custom unary operation whose result `value` and local `partial` derivative
+    /// have already been computed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use aad::Tape;
+    ///
+    /// let tape = Tape::new();
+    /// let x = tape.create_variable(2.0_f64);
+    /// let y = x.record_unary(x.value().powi(3), 3.0 * x.value().powi(2));
+    /// let grads = y.compute_gradients().unwrap();
+    /// assert_eq!(grads.get_gradient(&x).unwrap(), 12.0);
+    /// ```
+    pub fn record_unary(&self, value: F, partial: F) -> Self {
+        self.apply_unary_function(|_| value, |_| partial)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Records a custom binary operation whose result `value` and local `partials` with
+    /// respect to `self` and `rhs` have already been computed.
+    pub fn record_binary(&self, rhs: &Self, value: F, partials: (F, F)) -> Self {
+        self.apply_binary_function(rhs, |_, _| value, |_, _| partials)
     }
 }
 
@@ -143,27 +293,128 @@ impl<F: Copy + One + Zero> Variable<'_, F> {
     /// # Errors
     ///
     /// * Returns `GradientError::MissingIndex` if this variable has no index in the computation graph
+    /// * Returns `GradientError::StaleVariable` if this variable was invalidated by rewinding its tape
     pub fn compute_gradients(&self) -> Result<Gradients<F>, GradientError> {
-        let (var_index, tape) = self.index.ok_or(GradientError::MissingIndex)?;
-        let operations = &tape.operations.borrow();
-        let mut grads = vec![F::zero(); operations.len()];
-        grads[var_index] = F::one();
-
-        for (i, operation) in (*operations).iter().enumerate().rev() {
-            let grad = grads[i];
-            if grad.is_zero() {
-                continue;
-            }
-            for j in 0..2 {
-                let (idx, val) = operation.0[j];
-                if idx == usize::MAX {
-                    continue;
-                }
-                grads[idx] = grads[idx] + val * grad;
-            }
+        let (_, tape) = self.index.ok_or(GradientError::MissingIndex)?;
+        tape.backward(&[(*self, F::one())])
+    }
+
+    #[inline]
+    /// Computes gradients like [`Variable::compute_gradients`], reusing the allocation of
+    /// `buffer`.
+    ///
+    /// Intended for loops that sweep the same tape many times: only the adjoints written by
+    /// the previous sweep into `buffer` are reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - Buffer the adjoints are accumulated in
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Gradients<F, &[F]>)` - The computed gradients, borrowed from `buffer`
+    /// * `Err(GradientError)` - If this variable has no index in the computation graph
+    ///
+    /// # Errors
+    ///
+    /// * Returns `GradientError::MissingIndex` if this variable has no index in the computation graph
+    /// * Returns `GradientError::StaleVariable` if this variable was invalidated by rewinding its tape
+    pub fn compute_gradients_into<'b>(
+        &self,
+        buffer: &'b mut GradientBuffer<F>,
+    ) -> Result<Gradients<F, &'b [F]>, GradientError> {
+        let (_, tape) = self.index.ok_or(GradientError::MissingIndex)?;
+        tape.backward_into(&[(*self, F::one())], buffer)
+    }
+}
+
+/// Returns the [`TypeId`] of `T` with its lifetimes erased.
+///
+/// Variables can be compared with any type their value can be compared with, so the comparands
+/// of comparisons that can be flagged are picked out by type rather than by a trait bound.
+fn erased_type_id<T: ?Sized>() -> TypeId {
+    trait Erased {
+        fn erased_id(&self) -> TypeId
+        where
+            Self: 'static;
+    }
+
+    impl<T: ?Sized> Erased for PhantomData<T> {
+        fn erased_id(&self) -> TypeId
+        where
+            Self: 'static,
+        {
+            TypeId::of::<T>()
+        }
+    }
+
+    let phantom: &dyn Erased = &PhantomData::<T>;
+    // SAFETY: `erased_id` reads no data, and type ids do not depend on lifetimes.
+    let phantom: &(dyn Erased + 'static) = unsafe { std::mem::transmute(phantom) };
+    phantom.erased_id()
+}
+
+#[inline]
+/// Returns `value` widened to `f64` if `V` is `f32` or `f64`.
+fn float_value<V>(value: &V) -> Option<f64> {
+    let id = erased_type_id::<V>();
+    let value = std::ptr::from_ref(value);
+    // SAFETY: the type id shows that `value` points to the type it is read as.
+    unsafe {
+        if id == TypeId::of::<f64>() {
+            Some(value.cast::<f64>().read())
+        } else if id == TypeId::of::<f32>() {
+            Some(f64::from(value.cast::<f32>().read()))
+        } else {
+            None
+        }
+    }
+}
+
+impl<F> Variable<'_, F> {
+    #[inline]
+    /// Flags a comparison of `self` with `other` on a tape created with
+    /// [`Tape::with_op_kinds`], so that a [`RecordedFunction`](crate::replay::RecordedFunction)
+    /// can detect when new inputs would take another branch.
+    ///
+    /// Only comparisons between `f32` or `f64` values and variables of the same type are
+    /// flagged. Returns the outcome `test` computes from both values, or `None` if the
+    /// comparison is not flagged.
+    fn flag_comparison<T, R>(
+        &self,
+        other: &T,
+        test: impl FnOnce(f64, f64) -> (R, BranchTest),
+    ) -> Option<R> {
+        let variable = (erased_type_id::<T>() == erased_type_id::<Self>()).then(|| {
+            // SAFETY: `T` is `Self` up to lifetimes, which do not change its layout.
+            unsafe { &*std::ptr::from_ref(other).cast::<Self>() }
+        });
+        let (_, tape) = self.index.or_else(|| variable?.index)?;
+        if !tape.records_op_kinds() {
+            return None;
         }
+        let lhs = float_value(&self.value)?;
+        let (rhs, operand) = match variable {
+            Some(variable) => (float_value(&variable.value)?, variable.operand()),
+            None if erased_type_id::<T>() == erased_type_id::<F>() => {
+                // SAFETY: `T` is `F`, which is `f32` or `f64` since `lhs` was read.
+                let value = unsafe { std::ptr::from_ref(other).cast::<F>().read() };
+                (float_value(other)?, Operand::Constant(value))
+            }
+            None => return None,
+        };
+        let (outcome, test) = test(lhs, rhs);
+        tape.record_branch(self.operand(), operand, test);
+        Some(outcome)
+    }
 
-        Ok(Gradients(grads))
+    /// Returns `self` as the operand of a flagged comparison, where `F` is `f32` or `f64`.
+    fn operand(&self) -> Operand<F> {
+        match self.live_index() {
+            Some((index, _)) => Operand::Node(index),
+            // SAFETY: `F` is `f32` or `f64`, so reading a copy of the value is sound.
+            None => Operand::Constant(unsafe { std::ptr::from_ref(&self.value).read() }),
+        }
     }
 }
 
@@ -172,14 +423,14 @@ macro_rules! impl_partial_ord {
         impl<'a> PartialOrd<Variable<'a, $scalar>> for $scalar {
             #[inline]
             fn partial_cmp(&self, other: &Variable<'a, $scalar>) -> Option<std::cmp::Ordering> {
-                self.partial_cmp(&other.value)
+                other.partial_cmp(self).map(Ordering::reverse)
             }
         }
 
         impl<'a> PartialEq<Variable<'a, $scalar>> for $scalar {
             #[inline]
             fn eq(&self, other: &Variable<'a, $scalar>) -> bool {
-                self == &other.value
+                other == self
             }
         }
     };
@@ -196,7 +447,7 @@ macro_rules! impl_partial_ord_for_variable {
                 &self,
                 other: &Variable<'a, Variable<'b, $scalar>>,
             ) -> Option<std::cmp::Ordering> {
-                self.partial_cmp(&other.value)
+                other.partial_cmp(self).map(Ordering::reverse)
             }
         }
     };
@@ -207,7 +458,7 @@ impl_partial_ord_for_variable!(f64);
 impl<'a, 'b> PartialEq<Variable<'a, Variable<'b, f64>>> for f64 {
     #[inline]
     fn eq(&self, other: &Variable<'a, Variable<'b, f64>>) -> bool {
-        self == &other.value
+        other == self
     }
 }
 
@@ -216,7 +467,6 @@ where
     Self: Add<Self, Output = Self>,
 {
     #[inline]
-    #[must_use]
     fn zero() -> Self {
         Self::constant(F::zero())
     }
@@ -237,7 +487,6 @@ where
     Self: Mul<Self, Output = Self>,
 {
     #[inline]
-    #[must_use]
     fn one() -> Self {
         Self::constant(F::one())
     }
@@ -260,7 +509,12 @@ impl<F> Variable<'_, F> {
     #[inline]
     #[must_use]
     pub fn constant(value: F) -> Self {
-        Self { index: None, value }
+        Self {
+            index: None,
+            generation: 0,
+            value,
+            brand: PhantomData,
+        }
     }
 }
 
@@ -284,14 +538,20 @@ where
 {
     #[inline]
     fn partial_cmp(&self, other: &T) -> Option<Ordering> {
-        self.value.partial_cmp(other)
+        self.flag_comparison(other, |lhs, rhs| {
+            let ordering = lhs.partial_cmp(&rhs);
+            (ordering, BranchTest::Ordering(ordering))
+        })
+        .unwrap_or_else(|| self.value.partial_cmp(other))
     }
 }
 
 impl<T, F: PartialEq<T>> PartialEq<T> for Variable<'_, F> {
     #[inline]
     fn eq(&self, other: &T) -> bool {
-        self.value == *other
+        #[allow(clippy::float_cmp)]
+        self.flag_comparison(other, |lhs, rhs| (lhs == rhs, BranchTest::Eq(lhs == rhs)))
+            .unwrap_or_else(|| self.value == *other)
     }
 }
 
@@ -316,4 +576,218 @@ mod tests {
             .expect("Failed to get second gradient");
         assert_eq!(grad2, 2.0);
     }
+
+    #[test]
+    #[allow(clippy::float_cmp, clippy::many_single_char_names)]
+    fn test_apply_capturing_closures() {
+        let grid = [0.0, 1.0, 2.0];
+        let values = [1.0, 3.0, 4.0];
+        let interpolate = |x: f64| {
+            let i = grid
+                .iter()
+                .rposition(|g| *g <= x)
+                .unwrap()
+                .min(grid.len() - 2);
+            let slope = (values[i + 1] - values[i]) / (grid[i + 1] - grid[i]);
+            (values[i] + slope * (x - grid[i]), slope)
+        };
+
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[1.5_f64, 0.5]);
+        let z = x.apply_unary_function(|v| interpolate(v).0, |v| interpolate(v).1);
+        assert_eq!(z.value(), 3.5);
+
+        let weight = 2.0;
+        let w = z.apply_binary_function(&y, |a, b| weight * a * b, |a, b| (weight * b, weight * a));
+        let s = w.apply_scalar_function(|a, p| a * p * weight, |_, p| p * weight, 3.0);
+
+        let grads = s.compute_gradients().unwrap();
+        let [dx, dy] = grads.get_gradients(&[x, y]).unwrap();
+        assert_eq!(dx, 6.0 * weight * 0.5 * 1.0);
+        assert_eq!(dy, 6.0 * weight * 3.5);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_record_precomputed() {
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[2.0_f64, 3.0]);
+        let z = x.record_binary(&y, x.value() * y.value(), (y.value(), x.value()));
+        let w = z.record_unary(z.value().ln(), z.value().recip());
+        assert_eq!(w.value(), 6.0_f64.ln());
+
+        let grads = w.compute_gradients().unwrap();
+        assert_eq!(grads.get_gradients(&[x, y]).unwrap(), [0.5, 1.0 / 3.0]);
+        assert!(
+            Variable::constant(1.0)
+                .record_unary(2.0, 3.0)
+                .index
+                .is_none()
+        );
+    }
+
+    macro_rules! test_cross_tape_panics {
+        ($($name:ident: |$x:ident, $y:ident| $body:expr;)*) => {
+            $(
+                #[test]
+                #[cfg(debug_assertions)]
+                #[should_panic(expected = "different tapes")]
+                fn $name() {
+                    let tape = Tape::new();
+                    let other = Tape::new();
+                    #[allow(unused_mut)]
+                    let mut $x = tape.create_variable(2.0_f64);
+                    let $y = other.create_variable(3.0_f64);
+                    let _ = $body;
+                }
+            )*
+        };
+    }
+
+    macro_rules! test_stale_variable_propagates {
+        ($($name:ident: |$x:ident, $y:ident| $body:expr;)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let tape = Tape::new();
+                    let $x = tape.create_variable(2.0_f64);
+                    let mark = tape.mark();
+                    #[allow(unused_mut)]
+                    let mut $y = $x * 3.0;
+                    tape.rewind_to(mark);
+                    let z = $x * 5.0;
+                    let w: Variable<'_, f64> = $body;
+                    assert!(matches!(
+                        w.compute_gradients().err(),
+                        Some(GradientError::StaleVariable(_))
+                    ));
+                    // The stale operand does not leak into later operations.
+                    let v = z * $x;
+                    assert_eq!(v.compute_gradients().unwrap().get_gradient(&$x), Ok(20.0));
+                }
+            )*
+        };
+    }
+
+    test_stale_variable_propagates! {
+        test_stale_mul: |x, y| y * x;
+        test_stale_mul_assign: |x, y| {
+            y *= x;
+            y
+        };
+        test_stale_scalar_add: |_x, y| y + 1.0;
+        test_stale_scalar_div: |_x, y| 1.0 / y;
+        test_stale_neg: |_x, y| -y;
+        test_stale_sin: |_x, y| y.sin();
+        test_stale_sum: |x, y| [x, y].into_iter().sum();
+        test_stale_external: |x, y| {
+            x.index.unwrap().1.record_external(&[x, y], &[1.0], |w: &[f64]| w.to_vec())[0]
+        };
+    }
+
+    #[test]
+    fn test_stale_comparison_is_not_flagged() {
+        let tape = Tape::new().with_op_kinds();
+        let x = tape.create_variable(2.0_f64);
+        let mark = tape.mark();
+        let y = x * 3.0;
+        tape.rewind_to(mark);
+        let _ = x * 5.0;
+        assert!(y > x);
+        assert!(x < 3.0);
+        let branches = tape.ops.borrow().as_ref().unwrap().branch_count();
+        assert_eq!(branches, 1);
+    }
+
+    #[test]
+    fn test_comparisons_are_flagged_once() {
+        let tape = Tape::new().with_op_kinds();
+        let [x, y] = tape.create_variables(&[2.0_f32, 3.0]);
+        assert!(x < y);
+        assert!(2.5 < y);
+        assert!(Variable::constant(1.0) < x);
+        assert!(x != 3.0);
+        let branches = || tape.ops.borrow().as_ref().unwrap().branch_count();
+        assert_eq!(branches(), 4);
+
+        let plain = Tape::new();
+        let z = plain.create_variable(1.0_f32);
+        assert!(z < 2.0);
+        assert_eq!(branches(), 4);
+    }
+
+    #[test]
+    fn test_compare_with_foreign_comparand() {
+        #[derive(PartialEq, PartialOrd)]
+        struct Threshold(f64);
+
+        impl PartialEq<Threshold> for f64 {
+            fn eq(&self, other: &Threshold) -> bool {
+                *self == other.0
+            }
+        }
+
+        impl PartialOrd<Threshold> for f64 {
+            fn partial_cmp(&self, other: &Threshold) -> Option<Ordering> {
+                self.partial_cmp(&other.0)
+            }
+        }
+
+        let tape = Tape::new().with_op_kinds();
+        let x = tape.create_variable(2.0_f64);
+        assert!(x < Threshold(3.0));
+        assert!(x == Threshold(2.0));
+        assert_eq!(tape.ops.borrow().as_ref().unwrap().branch_count(), 0);
+    }
+
+    test_cross_tape_panics! {
+        test_cross_tape_add: |x, y| x + y;
+        test_cross_tape_add_assign: |x, y| x += y;
+        test_cross_tape_sub: |x, y| x - y;
+        test_cross_tape_sub_assign: |x, y| x -= y;
+        test_cross_tape_mul: |x, y| x * y;
+        test_cross_tape_mul_assign: |x, y| x *= y;
+        test_cross_tape_div: |x, y| x / y;
+        test_cross_tape_div_assign: |x, y| x /= y;
+        test_cross_tape_hypot: |x, y| x.hypot(y);
+    }
+
+    #[test]
+    fn test_try_ops_reject_tape_mismatch() {
+        let tape = Tape::new();
+        let other = Tape::new();
+        let x = tape.create_variable(2.0_f64);
+        let y = other.create_variable(3.0_f64);
+
+        assert_eq!(x.try_add(&y).err(), Some(GradientError::TapeMismatch));
+        assert_eq!(x.try_sub(&y).err(), Some(GradientError::TapeMismatch));
+        assert_eq!(x.try_mul(&y).err(), Some(GradientError::TapeMismatch));
+        assert_eq!(x.try_div(&y).err(), Some(GradientError::TapeMismatch));
+        assert_eq!(
+            x.try_apply_binary_function(&y, f64::hypot, |a, b| (a, b))
+                .err(),
+            Some(GradientError::TapeMismatch)
+        );
+        assert_eq!(tape.len(), 1);
+        assert_eq!(other.len(), 1);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_try_ops_on_same_tape() {
+        let tape = Tape::new();
+        let [x, y] = tape.create_variables(&[2.0_f64, 4.0]);
+        let c = Variable::constant(5.0);
+
+        let z = x.try_add(&y).unwrap();
+        let z = z.try_mul(&x).unwrap();
+        let z = z.try_sub(&c).unwrap();
+        let z = z.try_div(&y).unwrap();
+        assert_eq!(z.value(), ((2.0 + 4.0) * 2.0 - 5.0) / 4.0);
+
+        let grads = z.compute_gradients().unwrap();
+        let [dx, dy] = grads.get_gradients(&[x, y]).unwrap();
+        assert_eq!(dx, (2.0 * 2.0 + 4.0) / 4.0);
+        assert_eq!(dy, (2.0 * 4.0 - ((2.0 + 4.0) * 2.0 - 5.0)) / 16.0);
+    }
 }
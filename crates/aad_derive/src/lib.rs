@@ -10,28 +10,38 @@ struct ReplaceBaseTypeFolder {
 
 impl Fold for ReplaceBaseTypeFolder {
     fn fold_expr(&mut self, expr: syn::Expr) -> syn::Expr {
-        if let syn::Expr::Lit(expr_lit) = &expr {
-            if let syn::Lit::Float(float_lit) = &expr_lit.lit {
-                return syn::parse_quote! { #float_lit };
-            }
+        if let syn::Expr::Lit(expr_lit) = &expr
+            && let syn::Lit::Float(float_lit) = &expr_lit.lit
+        {
+            return syn::parse_quote! { #float_lit };
         }
         syn::fold::fold_expr(self, expr)
     }
 
     fn fold_type(&mut self, ty: syn::Type) -> syn::Type {
-        if let syn::Type::Path(type_path) = &ty {
-            if let Some(seg) = type_path.path.segments.last() {
-                if seg.ident == self.base_type {
-                    return syn::parse_str::<syn::Type>(&self.s_ident.to_string()).unwrap();
-                }
-            }
+        if let syn::Type::Path(type_path) = &ty
+            && let Some(seg) = type_path.path.segments.last()
+            && seg.ident == self.base_type
+        {
+            return syn::parse_str::<syn::Type>(&self.s_ident.to_string()).unwrap();
         }
         syn::fold::fold_type(self, ty)
     }
 }
 
 #[proc_macro_attribute]
-pub fn autodiff(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn autodiff(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let checkpoint = if attr.is_empty() {
+        false
+    } else {
+        let option = parse_macro_input!(attr as Ident);
+        if option != "checkpoint" {
+            return syn::Error::new(option.span(), "Unknown option, expected `checkpoint`")
+                .to_compile_error()
+                .into();
+        }
+        true
+    };
     let input_fn = parse_macro_input!(item as ItemFn);
     let vis = &input_fn.vis;
     let sig = &input_fn.sig;
@@ -130,6 +140,17 @@ pub fn autodiff(_attr: TokenStream, item: TokenStream) -> TokenStream {
     };
     let transformed_block = folder.fold_block(*block.clone());
 
+    if checkpoint {
+        return checkpointed(
+            vis,
+            sig,
+            &new_sig,
+            &where_clause,
+            &transformed_block,
+            &t_ident,
+        );
+    }
+
     let expanded = quote! {
         #vis #new_sig #where_clause {
             #transformed_block
@@ -139,6 +160,80 @@ pub fn autodiff(_attr: TokenStream, item: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Expands a function whose body is evaluated through `FloatLike::checkpoint`, so that on a
+/// tape it is recorded as a single checkpointed routine.
+fn checkpointed(
+    vis: &syn::Visibility,
+    sig: &syn::Signature,
+    new_sig: &syn::Signature,
+    where_clause: &syn::WhereClause,
+    block: &syn::Block,
+    t_ident: &Ident,
+) -> TokenStream {
+    if !sig.generics.params.is_empty() {
+        return syn::Error::new(
+            sig.generics.span(),
+            "Checkpointed functions cannot be generic",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let mut patterns = Vec::new();
+    let mut idents = Vec::new();
+    for arg in &sig.inputs {
+        if let syn::FnArg::Typed(pat_type) = arg {
+            let plain =
+                matches!(&*pat_type.ty, syn::Type::Path(path) if path.path.get_ident().is_some());
+            if !plain || get_base_type(&pat_type.ty).is_none() {
+                return syn::Error::new(
+                    pat_type.ty.span(),
+                    "Checkpointed functions take only f32 or f64 arguments",
+                )
+                .to_compile_error()
+                .into();
+            }
+            let syn::Pat::Ident(pat_ident) = &*pat_type.pat else {
+                return syn::Error::new(
+                    pat_type.pat.span(),
+                    "Checkpointed functions take only plain identifiers as arguments",
+                )
+                .to_compile_error()
+                .into();
+            };
+            patterns.push(&pat_type.pat);
+            idents.push(&pat_ident.ident);
+        }
+    }
+
+    let expanded = quote! {
+        #vis #new_sig #where_clause {
+            struct __AutodiffCheckpoint;
+
+            impl ::aad::float_like::Checkpoint<#t_ident> for __AutodiffCheckpoint {
+                fn eval<FloatType: ::aad::FloatLike<#t_ident>>(
+                    &self,
+                    inputs: &[FloatType],
+                ) -> ::std::vec::Vec<FloatType>
+                #where_clause
+                {
+                    let &[#(#patterns),*] = inputs else {
+                        ::std::unreachable!("checkpointed function called with the wrong number of inputs")
+                    };
+                    ::std::vec![(move || -> FloatType #block)()]
+                }
+            }
+
+            <FloatType as ::aad::FloatLike<#t_ident>>::checkpoint(
+                __AutodiffCheckpoint,
+                &[#(#idents),*],
+            )
+            .remove(0)
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
 fn get_base_type(ty: &syn::Type) -> Option<String> {
     match ty {
         syn::Type::Path(type_path) => {
@@ -147,10 +242,10 @@ fn get_base_type(ty: &syn::Type) -> Option<String> {
                 if ident == "f32" || ident == "f64" {
                     return Some(ident);
                 }
-                if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
-                    if let Some(syn::GenericArgument::Type(inner_ty)) = args.args.first() {
-                        return get_base_type(inner_ty);
-                    }
+                if let syn::PathArguments::AngleBracketed(args) = &seg.arguments
+                    && let Some(syn::GenericArgument::Type(inner_ty)) = args.args.first()
+                {
+                    return get_base_type(inner_ty);
                 }
             }
             None
@@ -0,0 +1,24 @@
+use aad::Tape;
+use aad::autodiff;
+use aad::replay::RecordedFunction;
+use std::path::Path;
+use std::{env, fs};
+
+include!("pricing.rs");
+
+fn main() {
+    println!("cargo::rerun-if-changed=pricing.rs");
+
+    let tape = Tape::new().with_op_kinds();
+    let inputs = tape.create_variables(&[100.0, 95.0, 0.03, 0.2, 1.5]);
+    let [spot, strike, rate, vol, expiry] = inputs;
+    let price = black_scholes_call(spot, strike, rate, vol, expiry);
+    let function = RecordedFunction::new(&tape, &inputs, &[price]).unwrap();
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(
+        Path::new(&out_dir).join("black_scholes_call_gradient.rs"),
+        function.to_rust("black_scholes_call_gradient", 0),
+    )
+    .unwrap();
+}
@@ -0,0 +1,21 @@
+/// Standard normal distribution function, from Abramowitz and Stegun 26.2.17.
+#[autodiff]
+fn norm_cdf(x: f64) -> f64 {
+    if x < 0.0 {
+        return 1.0 - norm_cdf(-x);
+    }
+    let t = 1.0 / (1.0 + 0.231_641_9 * x);
+    let poly = t
+        * (0.319_381_530
+            + t * (-0.356_563_782 + t * (1.781_477_937 + t * (-1.821_255_978 + t * 1.330_274_429))));
+    1.0 - 0.398_942_280_401_432_7 * (-0.5 * x * x).exp() * poly
+}
+
+/// Black-Scholes price of a European call.
+#[autodiff]
+fn black_scholes_call(spot: f64, strike: f64, rate: f64, vol: f64, expiry: f64) -> f64 {
+    let sqrt_expiry = expiry.sqrt();
+    let d1 = ((spot / strike).ln() + (rate + 0.5 * vol * vol) * expiry) / (vol * sqrt_expiry);
+    let d2 = d1 - vol * sqrt_expiry;
+    spot * norm_cdf(d1) - strike * (-rate * expiry).exp() * norm_cdf(d2)
+}
@@ -0,0 +1,20 @@
+use aad::{Tape, autodiff};
+
+#[autodiff]
+fn f(x: f64, y: f64) -> f64 {
+    x.powi(2) * y.sin()
+}
+
+#[test]
+fn main() {
+    let [dx, dy] = Tape::scope(|tape| {
+        let [x, y] = tape.create_variables(&[2.0, 3.0]);
+        let z = f(x, y);
+        z.compute_gradients()
+            .unwrap()
+            .get_gradients(&[x, y])
+            .unwrap()
+    });
+    assert_eq!(dx, 2.0 * 2.0 * 3.0_f64.sin());
+    assert_eq!(dy, 2.0_f64.powi(2) * 3.0_f64.cos());
+}
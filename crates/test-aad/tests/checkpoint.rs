@@ -0,0 +1,64 @@
+use aad::autodiff;
+
+#[autodiff]
+fn evolve(x: f64, rate: f64, vol: f64) -> f64 {
+    let mut x = x;
+    for i in 0..200 {
+        let shock = (f64::from(i) * 0.1).sin();
+        x += x * (rate * 0.01 + vol * 0.1 * shock);
+        if x < 0.0 {
+            return x * 0.0;
+        }
+    }
+    x
+}
+
+#[autodiff(checkpoint)]
+fn evolve_checkpointed(x: f64, rate: f64, vol: f64) -> f64 {
+    let mut x = x;
+    for i in 0..200 {
+        let shock = (f64::from(i) * 0.1).sin();
+        x += x * (rate * 0.01 + vol * 0.1 * shock);
+        if x < 0.0 {
+            return x * 0.0;
+        }
+    }
+    x
+}
+
+#[test]
+fn main() {
+    use aad::Tape;
+    use aad::gradients::GradientError;
+
+    let tape = Tape::default();
+    let [spot, rate, vol] = tape.create_variables(&[100.0, 0.05, 0.2]);
+    let price = (evolve(spot, rate, vol) - 100.0) * 0.5;
+    let expected = price.compute_gradients().unwrap();
+    let expected = expected.get_gradients(&[spot, rate, vol]).unwrap();
+
+    tape.clear();
+    let [spot, rate, vol] = tape.create_variables(&[100.0, 0.05, 0.2]);
+    let checkpointed = (evolve_checkpointed(spot, rate, vol) - 100.0) * 0.5;
+    assert_eq!(checkpointed.value(), price.value());
+    assert_eq!(tape.len(), 6);
+    let grads = checkpointed.compute_gradients().unwrap();
+    let grads = grads.get_gradients(&[spot, rate, vol]).unwrap();
+    for (grad, expected) in grads.iter().zip(expected) {
+        assert!((grad - expected).abs() <= 1e-12 * expected.abs());
+    }
+
+    assert_eq!(
+        evolve_checkpointed(100.0, 0.05, 0.2),
+        evolve(100.0, 0.05, 0.2)
+    );
+
+    let mark = tape.mark();
+    let stale = spot * 2.0;
+    tape.rewind_to(mark);
+    let _ = spot * 3.0;
+    assert!(matches!(
+        evolve_checkpointed(stale, rate, vol).compute_gradients(),
+        Err(GradientError::StaleVariable(_))
+    ));
+}
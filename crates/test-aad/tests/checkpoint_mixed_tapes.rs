@@ -0,0 +1,18 @@
+use aad::autodiff;
+
+#[autodiff(checkpoint)]
+fn scale(x: f64, factor: f64) -> f64 {
+    x * factor
+}
+
+#[test]
+#[should_panic(expected = "cannot combine variables recorded on different tapes")]
+fn main() {
+    use aad::Tape;
+
+    let tape = Tape::default();
+    let other = Tape::default();
+    let x = tape.create_variable(2.0);
+    let factor = other.create_variable(3.0);
+    let _ = scale(x, factor);
+}
@@ -0,0 +1,23 @@
+use aad::{Tape, autodiff};
+
+include!("../pricing.rs");
+include!(concat!(env!("OUT_DIR"), "/black_scholes_call_gradient.rs"));
+
+#[test]
+fn main() {
+    for point in [
+        [100.0, 95.0, 0.03, 0.2, 1.5],
+        [110.0, 100.0, 0.02, 0.25, 2.0],
+        [100.0, 100.0, 0.05, 0.1, 1.0],
+    ] {
+        let tape = Tape::new();
+        let inputs = tape.create_variables(&point);
+        let [spot, strike, rate, vol, expiry] = inputs;
+        let price = black_scholes_call(spot, strike, rate, vol, expiry);
+        let grads = price.compute_gradients().unwrap();
+
+        let (value, gradient) = black_scholes_call_gradient(&point);
+        assert_eq!(value, price.value());
+        assert_eq!(gradient.to_vec(), grads.get_gradients(&inputs).unwrap());
+    }
+}
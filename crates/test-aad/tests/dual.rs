@@ -0,0 +1,23 @@
+use aad::{Dual, autodiff};
+
+#[autodiff]
+fn f(x: f64, y: f64) -> f64 {
+    x.powi(2) * y.sin() + 2.0 * x / y - 1.0
+}
+
+#[test]
+fn main() {
+    use aad::Tape;
+    let tape = Tape::default();
+    let [x, y] = tape.create_variables(&[2.0, 3.0]);
+    let z = f(x, y);
+    let grads = z.compute_gradients().unwrap();
+
+    let [dx, dy] = Dual::<f64, 2>::variables(&[2.0, 3.0]);
+    let w = f(dx, dy);
+    assert_eq!(w.value(), z.value());
+    let expected = grads.get_gradients(&[x, y]).unwrap();
+    for (actual, expected) in w.tangents().iter().zip(expected) {
+        assert!((actual - expected).abs() < 1e-12);
+    }
+}
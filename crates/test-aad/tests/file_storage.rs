@@ -0,0 +1,28 @@
+use aad::autodiff;
+
+#[autodiff]
+fn path(x0: f64, vol: f64, drift: f64) -> f64 {
+    let mut x = x0;
+    for i in 0..1000 {
+        x *= (drift + vol * (f64::from(i) * 0.37).sin()).exp();
+    }
+    x * x.ln()
+}
+
+#[test]
+fn main() {
+    use aad::Tape;
+    use aad::storage::FileStorage;
+
+    let gradients = |tape: &Tape<f64>| {
+        let inputs = tape.create_variables(&[1.0, 0.2, 0.001]);
+        let [x0, vol, drift] = inputs;
+        let y = path(x0, vol, drift);
+        let grads = y.compute_gradients().unwrap();
+        (y.value(), grads.get_gradients(&inputs).unwrap())
+    };
+
+    let memory = Tape::new();
+    let file = Tape::with_storage(FileStorage::new().unwrap().with_block_len(64));
+    assert_eq!(gradients(&memory), gradients(&file));
+}
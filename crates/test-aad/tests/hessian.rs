@@ -0,0 +1,20 @@
+use aad::{autodiff, hessian, hvp};
+
+#[autodiff]
+fn f(x: f64, y: f64) -> f64 {
+    x.powi(2) * y.sin()
+}
+
+#[test]
+fn main() {
+    let [x, y] = [2.0_f64, 3.0];
+    let h = hessian(|v| f(v[0], v[1]), &[x, y]);
+    let expected = [
+        [2.0 * y.sin(), 2.0 * x * y.cos()],
+        [2.0 * x * y.cos(), -x.powi(2) * y.sin()],
+    ];
+    assert_eq!(h, expected);
+
+    let hv = hvp(|v| f(v[0], v[1]), &[x, y], &[1.0, 0.0]);
+    assert_eq!(hv, [expected[0][0], expected[1][0]]);
+}
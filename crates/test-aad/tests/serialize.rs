@@ -0,0 +1,33 @@
+use aad::Tape;
+use aad::gradients::Gradients;
+use aad::serialize::FormatError;
+use std::fs::{self, File};
+use std::io::BufWriter;
+
+#[test]
+fn main() {
+    let path = std::env::temp_dir().join(format!("aad-serialize-{}.tape", std::process::id()));
+    let tape = Tape::new();
+    let [x, y] = tape.create_variables(&[1.5_f64, 0.5]);
+    let z = (x * y).exp() / (x + y).sqrt();
+    let expected = z.compute_gradients().unwrap();
+    tape.write_to(BufWriter::new(File::create(&path).unwrap()))
+        .unwrap();
+
+    let loaded = Tape::<f64>::read_from(File::open(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    let z = loaded.variable_at(loaded.len() - 1, z.value()).unwrap();
+    let grads = z.compute_gradients().unwrap();
+    assert_eq!(grads.as_slice(), expected.as_slice());
+
+    let json = serde_json::to_string(&grads).unwrap();
+    let restored: Gradients<f64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.as_slice(), expected.as_slice());
+
+    let json = serde_json::to_string(&loaded).unwrap();
+    let restored: Tape<f64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.len(), tape.len());
+
+    let error = serde_json::from_str::<Tape<f32>>(&json).unwrap_err();
+    assert_eq!(error.to_string(), FormatError::ValueSize(8, 4).to_string());
+}
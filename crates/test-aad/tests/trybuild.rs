@@ -3,4 +3,5 @@ fn trybuild_tests() {
     let t = trybuild::TestCases::new();
 
     t.pass("tests/ui/pass/*.rs");
+    t.compile_fail("tests/ui/fail/*.rs");
 }
@@ -0,0 +1,10 @@
+use aad::autodiff;
+
+#[autodiff(checkpoint)]
+fn f(x: f64, weights: Vec<f64>) -> f64 {
+    weights.into_iter().fold(x, |acc, weight| acc * weight)
+}
+
+fn main() {
+    let _ = f(1.0, vec![2.0, 3.0]);
+}
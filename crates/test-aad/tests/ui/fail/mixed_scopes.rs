@@ -0,0 +1,16 @@
+use aad::{Tape, autodiff};
+
+#[autodiff]
+fn f(x: f64, y: f64) -> f64 {
+    x * y
+}
+
+fn main() {
+    Tape::scope(|outer| {
+        Tape::scope(|inner| {
+            let x = outer.create_variable(1.0);
+            let y = inner.create_variable(2.0);
+            f(x, y).value()
+        })
+    });
+}